@@ -0,0 +1,76 @@
+use crate::{
+    ray::Ray,
+    vec3::{Point3, Vec3},
+};
+
+// 轴对齐包围盒 axis-aligned bounding box
+#[derive(Clone, Copy, Debug)]
+pub struct Aabb {
+    pub min: Point3,
+    pub max: Point3,
+}
+
+impl Aabb {
+    pub fn new(a: Point3, b: Point3) -> Self {
+        Self {
+            min: Point3::min(&a, &b),
+            max: Point3::max(&a, &b),
+        }
+        .pad()
+    }
+
+    // 平面物体 (比如和坐标轴平行的三角形) 在某个轴上没有厚度, 给它补上一点避免 slab 测试失效
+    fn pad(mut self) -> Self {
+        let delta = 1e-4;
+        let d = self.max - self.min;
+        let pad = |x: f64| if x < delta { delta / 2.0 } else { 0.0 };
+        let v = Vec3::new(pad(d.x()), pad(d.y()), pad(d.z()));
+        self.min -= v;
+        self.max += v;
+        self
+    }
+
+    // 同时包住两个包围盒的最小包围盒
+    pub fn surrounding(a: &Self, b: &Self) -> Self {
+        Self {
+            min: Point3::min(&a.min, &b.min),
+            max: Point3::max(&a.max, &b.max),
+        }
+    }
+
+    pub fn centroid(&self) -> Point3 {
+        0.5 * (self.min + self.max)
+    }
+
+    // 最长的轴 0: x 1: y 2: z
+    pub fn longest_axis(&self) -> usize {
+        let d = self.max - self.min;
+        if d.x() > d.y() && d.x() > d.z() {
+            0
+        } else if d.y() > d.z() {
+            1
+        } else {
+            2
+        }
+    }
+
+    // slab 方法: 光线在每个轴上进出 slab 的区间取交集, 交集非空即命中
+    pub fn hit(&self, r: &Ray, mut min: f64, mut max: f64) -> bool {
+        for a in 0..3 {
+            let inv_d = 1.0 / r.direction()[a];
+            let mut t0 = (self.min[a] - r.origin()[a]) * inv_d;
+            let mut t1 = (self.max[a] - r.origin()[a]) * inv_d;
+            if inv_d < 0.0 {
+                std::mem::swap(&mut t0, &mut t1);
+            }
+
+            min = if t0 > min { t0 } else { min };
+            max = if t1 < max { t1 } else { max };
+            if max <= min {
+                return false;
+            }
+        }
+
+        true
+    }
+}
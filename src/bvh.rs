@@ -0,0 +1,236 @@
+use crate::{
+    aabb::Aabb,
+    hit::{Hittable, HittableList, Record},
+    ray::Ray,
+};
+
+// 层次包围盒 bounding volume hierarchy
+// 每个节点保存子树的包围盒, 光线没有命中包围盒时整个子树都可以跳过
+pub enum BvhNode<T> {
+    Empty,
+    Leaf(T),
+    Branch {
+        left: Box<BvhNode<T>>,
+        right: Box<BvhNode<T>>,
+        bbox: Aabb,
+    },
+    // 只出现在根节点: 没有包围盒的物体 (比如平面) 每条光线都单独测试, 其余物体放在 bvh 中
+    Unbounded {
+        objects: Vec<T>,
+        bvh: Box<BvhNode<T>>,
+    },
+}
+
+impl<T: Hittable> BvhNode<T> {
+    pub fn new(list: HittableList<T>) -> Self {
+        let mut bounded = vec![];
+        let mut unbounded = vec![];
+        for object in list {
+            match object.bounding_box() {
+                Some(bbox) => bounded.push((object, bbox)),
+                None => unbounded.push(object),
+            }
+        }
+
+        let bvh = Self::build(bounded);
+        match unbounded.is_empty() {
+            true => bvh,
+            false => Self::Unbounded {
+                objects: unbounded,
+                bvh: Box::new(bvh),
+            },
+        }
+    }
+
+    // 按所有物体中心点分布最长的轴排序, 从中位数处一分为二
+    fn build(mut objects: Vec<(T, Aabb)>) -> Self {
+        match objects.len() {
+            0 => return Self::Empty,
+            1 => return Self::Leaf(objects.pop().unwrap().0),
+            _ => {}
+        }
+
+        let centroids = objects
+            .iter()
+            .map(|(_, bbox)| bbox.centroid())
+            .fold(None, |acc: Option<Aabb>, c| match acc {
+                Some(acc) => Some(Aabb::surrounding(&acc, &Aabb::new(c, c))),
+                None => Some(Aabb::new(c, c)),
+            })
+            .unwrap();
+
+        let axis = centroids.longest_axis();
+        objects.sort_by(|(_, a), (_, b)| a.centroid()[axis].total_cmp(&b.centroid()[axis]));
+
+        let bbox = objects
+            .iter()
+            .skip(1)
+            .fold(objects[0].1, |acc, (_, bbox)| Aabb::surrounding(&acc, bbox));
+
+        let rest = objects.split_off(objects.len() / 2);
+        Self::Branch {
+            left: Box::new(Self::build(objects)),
+            right: Box::new(Self::build(rest)),
+            bbox,
+        }
+    }
+}
+
+impl<T: Hittable> From<HittableList<T>> for BvhNode<T> {
+    fn from(list: HittableList<T>) -> Self {
+        Self::new(list)
+    }
+}
+
+impl<T: Hittable> BvhNode<T> {
+    // 用 hit 函数求子树中最近的交点
+    fn closest<'a, F>(&'a self, ray: &Ray, min: f64, max: f64, hit: &F) -> Option<Record<'a>>
+    where
+        F: Fn(&'a T, &Ray, f64, f64) -> Option<Record<'a>>,
+    {
+        match self {
+            Self::Empty => None,
+            Self::Leaf(object) => hit(object, ray, min, max),
+            Self::Branch { left, right, bbox } => {
+                if !bbox.hit(ray, min, max) {
+                    return None;
+                }
+
+                let left = left.closest(ray, min, max, hit);
+                let closest = left.as_ref().map_or(max, |rec| rec.t);
+                right.closest(ray, min, closest, hit).or(left)
+            }
+            Self::Unbounded { objects, bvh } => {
+                let mut rec = bvh.closest(ray, min, max, hit);
+                for object in objects {
+                    let closest = rec.as_ref().map_or(max, |rec| rec.t);
+                    if let Some(hit_rec) = hit(object, ray, min, closest) {
+                        rec = Some(hit_rec);
+                    }
+                }
+                rec
+            }
+        }
+    }
+}
+
+impl<T: Hittable> Hittable for BvhNode<T> {
+    fn hit(&self, ray: &Ray, min: f64, max: f64) -> Option<Record<'_>> {
+        self.closest(ray, min, max, &|o, r, min, max| o.hit(r, min, max))
+    }
+
+    fn hit_surface(&self, ray: &Ray, min: f64, max: f64) -> Option<Record<'_>> {
+        self.closest(ray, min, max, &|o, r, min, max| o.hit_surface(r, min, max))
+    }
+
+    fn transmittance(&self, ray: &Ray, min: f64, max: f64) -> f64 {
+        match self {
+            Self::Empty => 1.0,
+            Self::Leaf(object) => object.transmittance(ray, min, max),
+            Self::Branch { left, right, bbox } => match bbox.hit(ray, min, max) {
+                true => left.transmittance(ray, min, max) * right.transmittance(ray, min, max),
+                false => 1.0,
+            },
+            Self::Unbounded { objects, bvh } => {
+                let tr: f64 = objects
+                    .iter()
+                    .map(|o| o.transmittance(ray, min, max))
+                    .product();
+                tr * bvh.transmittance(ray, min, max)
+            }
+        }
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        match self {
+            Self::Empty => None,
+            Self::Leaf(object) => object.bounding_box(),
+            Self::Branch { bbox, .. } => Some(*bbox),
+            Self::Unbounded { .. } => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::{
+        material::Lambertian,
+        plane::Plane,
+        quad::Quad,
+        random,
+        sphere::Sphere,
+        vec3::{Color, Point3, Vec3},
+    };
+
+    fn random_point(size: f64) -> Point3 {
+        Point3::new(
+            random::random_range(-size, size),
+            random::random_range(-size, size),
+            random::random_range(-size, size),
+        )
+    }
+
+    // BvhNode 与直接遍历 HittableList 求出的最近交点应该完全相同
+    #[test]
+    fn closest_hit_matches_list() {
+        random::reseed(1, 0);
+        let material = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+
+        let mut objects: Vec<Arc<dyn Hittable>> = vec![];
+        for _ in 0..200 {
+            let radius = random::random_range(0.1, 1.0);
+            objects.push(Arc::new(Sphere::new(
+                random_point(10.0),
+                radius,
+                material.clone(),
+            )));
+        }
+        for _ in 0..50 {
+            let u = random_point(1.0);
+            let v = random_point(1.0);
+            objects.push(Arc::new(Quad::new(
+                random_point(10.0),
+                u,
+                v,
+                material.clone(),
+            )));
+        }
+        objects.push(Arc::new(Plane::new(
+            Point3::new(0.0, -10.0, 0.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            material,
+        )));
+
+        let list: HittableList<_> = objects.iter().cloned().collect();
+        let bvh = BvhNode::new(objects.into_iter().collect());
+        assert!(matches!(bvh, BvhNode::Unbounded { .. }));
+
+        let mut hits = 0;
+        for _ in 0..2000 {
+            let r = Ray::new(random_point(15.0), Vec3::random_unit_vector(), 0.0);
+            let expected = list.hit(&r, 0.001, f64::INFINITY);
+            let actual = bvh.hit(&r, 0.001, f64::INFINITY);
+            match (expected, actual) {
+                (Some(a), Some(b)) => {
+                    assert_eq!(a.t, b.t);
+                    assert!((a.normal - b.normal).near_zero());
+                    hits += 1;
+                }
+                (None, None) => {}
+                (a, b) => panic!("list hit {}, bvh hit {}", a.is_some(), b.is_some()),
+            }
+        }
+        assert!(hits > 100);
+    }
+
+    #[test]
+    fn empty() {
+        let bvh: BvhNode<Arc<dyn Hittable>> = BvhNode::new(HittableList::new());
+        let r = Ray::new(Point3::default(), Vec3::new(1.0, 0.0, 0.0), 0.0);
+        assert!(bvh.hit(&r, 0.001, f64::INFINITY).is_none());
+        assert!(bvh.bounding_box().is_none());
+    }
+}
@@ -1,6 +1,7 @@
 use std::f64::consts::PI;
 
 use crate::{
+    random,
     ray::Ray,
     vec3::{Point3, Vec3},
 };
@@ -12,6 +13,9 @@ pub struct Camera {
     vertical: Vec3,
     lens_radius: f64, // 透镜半径
 
+    time0: f64, // 快门打开时刻
+    time1: f64, // 快门关闭时刻
+
     u: Vec3,
     v: Vec3,
     #[allow(dead_code)]
@@ -65,6 +69,17 @@ impl Camera {
             v,
             w,
             lens_radius,
+            time0: 0.0,
+            time1: 0.0,
+        }
+    }
+
+    // 快门在 [time0, time1] 之间打开, 光线的时刻在其中均匀随机
+    pub fn with_shutter(self, time0: f64, time1: f64) -> Self {
+        Self {
+            time0,
+            time1,
+            ..self
         }
     }
 
@@ -75,9 +90,11 @@ impl Camera {
         let rd = self.lens_radius * Vec3::random_in_uint_disk();
         let offset = self.u * rd.x() + self.v * rd.y();
         let v = s * self.horizontal + t * self.vertical;
+        let time = self.time0 + (self.time1 - self.time0) * random::random_double();
         Ray::new(
             self.origin + offset,
             self.lower_left_corner + v - self.origin - offset,
+            time,
         )
     }
 }
@@ -0,0 +1,102 @@
+use std::f64::consts::PI;
+
+use crate::{spectrum, vec3::Color};
+
+// RGB 渲染时每个通道的代表波长和带宽 (nm)
+const RGB_BANDS: [(f64, f64); 3] = [(610.0, 40.0), (550.0, 35.0), (465.0, 25.0)];
+
+// 薄膜下面的基底
+#[derive(Clone, Copy, Debug)]
+pub enum FilmBase {
+    Dielectric(f64),  // 基底的折射率
+    Conductor(Color), // 金属垂直入射时的反射率, 相位按半波损失近似
+}
+
+// 表面上的一层薄膜 (肥皂泡, 油膜, 镜头镀膜), 薄膜上下表面的反射光相互干涉
+#[derive(Clone, Copy, Debug)]
+pub struct Film {
+    pub thickness: f64, // 厚度 (nm)
+    pub ior: f64,       // 折射率
+}
+
+impl Film {
+    pub fn new(thickness: f64, ior: f64) -> Self {
+        Self { thickness, ior }
+    }
+
+    // 从折射率为 n1 的一侧以 cos_i 入射时的反射率, 对 s 和 p 偏振取平均
+    // 给出波长时按 Airy 公式精确计算, 返回灰色; 否则按 RGB 三个波段近似,
+    // 波段内干涉项的平均值随光程差按高斯函数衰减, 厚膜的颜色会逐渐消失
+    pub fn reflectance(
+        &self,
+        cos_i: f64,
+        n1: f64,
+        base: &FilmBase,
+        wavelength: Option<f64>,
+    ) -> Color {
+        let n2 = self.ior;
+        let cos1 = cos_i.clamp(0.0, 1.0);
+        let sin2_2 = (n1 / n2).powi(2) * (1.0 - cos1 * cos1);
+        if sin2_2 >= 1.0 {
+            return Color::new(1.0, 1.0, 1.0);
+        }
+
+        let cos2 = (1.0 - sin2_2).sqrt();
+        let (r12s, r12p) = amplitude(n1, cos1, n2, cos2);
+        let opd = 2.0 * n2 * self.thickness * cos2; // 两束反射光的光程差
+
+        let channel = |c: usize| {
+            let (r23s, r23p) = match *base {
+                FilmBase::Dielectric(n3) => {
+                    let sin2_3 = (n2 / n3).powi(2) * (1.0 - cos2 * cos2);
+                    if sin2_3 >= 1.0 {
+                        return 1.0;
+                    }
+                    amplitude(n2, cos2, n3, (1.0 - sin2_3).sqrt())
+                }
+                FilmBase::Conductor(f0) => {
+                    let f0 = match wavelength {
+                        Some(lambda) => spectrum::upsample(&f0, lambda),
+                        None => f0[c],
+                    };
+                    let f = f0 + (1.0 - f0) * (1.0 - cos2).powi(5);
+                    let r = -f.clamp(0.0, 1.0).sqrt();
+                    (r, r)
+                }
+            };
+
+            let cos_phase = match wavelength {
+                Some(lambda) => (2.0 * PI * opd / lambda).cos(),
+                None => {
+                    let (lambda, width) = RGB_BANDS[c];
+                    let sigma = width / (lambda * lambda); // 波数的标准差
+                    let damping = (-2.0 * (PI * sigma * opd).powi(2)).exp();
+                    (2.0 * PI * opd / lambda).cos() * damping
+                }
+            };
+            0.5 * (airy(r12s, r23s, cos_phase) + airy(r12p, r23p, cos_phase))
+        };
+
+        match wavelength {
+            Some(_) => {
+                let r = channel(0);
+                Color::new(r, r, r)
+            }
+            None => Color::new(channel(0), channel(1), channel(2)),
+        }
+    }
+}
+
+// 界面上 s 和 p 偏振的振幅反射系数
+fn amplitude(n1: f64, cos1: f64, n2: f64, cos2: f64) -> (f64, f64) {
+    let rs = (n1 * cos1 - n2 * cos2) / (n1 * cos1 + n2 * cos2);
+    let rp = (n2 * cos1 - n1 * cos2) / (n2 * cos1 + n1 * cos2);
+    (rs, rp)
+}
+
+// 薄膜上下表面多次反射叠加后的反射率
+fn airy(r12: f64, r23: f64, cos_phase: f64) -> f64 {
+    let cross = 2.0 * r12 * r23 * cos_phase;
+    let r = (r12 * r12 + r23 * r23 + cross) / (1.0 + r12 * r12 * r23 * r23 + cross);
+    r.clamp(0.0, 1.0)
+}
@@ -1,23 +1,144 @@
-use std::rc::Rc;
+use std::sync::Arc;
+
+use rand::Rng;
 
 use crate::{
+    aabb::Aabb,
     material::Material,
+    random,
     ray::Ray,
     vec3::{Point3, Vec3},
 };
 
-pub struct Record {
+#[derive(Clone, Copy)]
+pub struct Record<'a> {
     pub p: Point3, // 撞击点
     pub t: f64,    // 光源到到撞击对象的距离
 
     pub normal: Vec3,     // 法线
     pub front_face: bool, // true 表示法线向外 false 表示法线向内
 
-    pub material: Rc<dyn Material>,
+    pub u: f64, // 表面纹理坐标
+    pub v: f64,
+
+    pub dpdu: Vec3, // 撞击点沿纹理坐标 u, v 方向的切向量 ∂p/∂u, ∂p/∂v, 用于法线贴图和凹凸贴图
+    pub dpdv: Vec3, // 没有参数化的表面上为零向量
+
+    pub material: &'a dyn Material,
+}
+
+impl<'a> Record<'a> {
+    // 根据光线方向和向外的法线确定撞击的是正面还是背面, 法线始终与光线方向相反
+    // 切向量默认为零, 有参数化的形状用 with_tangents 设置
+    pub fn new(
+        r: &Ray,
+        t: f64,
+        outward_normal: Vec3,
+        (u, v): (f64, f64),
+        material: &'a dyn Material,
+    ) -> Self {
+        let front_face = Vec3::dot(r.direction(), &outward_normal) < 0.0;
+        let normal = match front_face {
+            true => outward_normal,
+            false => -outward_normal,
+        };
+
+        Self {
+            p: r.at(t),
+            t,
+            normal,
+            front_face,
+            u,
+            v,
+            dpdu: Vec3::default(),
+            dpdv: Vec3::default(),
+            material,
+        }
+    }
+
+    pub fn with_tangents(self, dpdu: Vec3, dpdv: Vec3) -> Self {
+        Self { dpdu, dpdv, ..self }
+    }
+}
+
+pub trait Hittable: Send + Sync {
+    fn hit(&self, ray: &Ray, min: f64, max: f64) -> Option<Record<'_>>;
+
+    // 物体的包围盒, 无限大的物体返回 None
+    fn bounding_box(&self) -> Option<Aabb>;
+
+    // 从 origin 沿 dir 方向看到该物体的概率密度 (对立体角), 用于光源采样
+    // 不支持采样的物体返回 0
+    fn pdf_value(&self, _origin: &Point3, _dir: &Vec3) -> f64 {
+        0.0
+    }
+
+    // 从 origin 指向物体上随机一点的方向, 分布与 pdf_value 一致
+    fn random(&self, _origin: &Point3) -> Vec3 {
+        Vec3::new(1.0, 0.0, 0.0)
+    }
+
+    // 只求与表面的交点, 跳过参与介质, 用于阴影光线
+    fn hit_surface(&self, ray: &Ray, min: f64, max: f64) -> Option<Record<'_>> {
+        self.hit(ray, min, max)
+    }
+
+    // 光线在 [min, max] 区间内穿过参与介质的透射率, 表面的遮挡由 hit_surface 判断
+    fn transmittance(&self, _ray: &Ray, _min: f64, _max: f64) -> f64 {
+        1.0
+    }
 }
 
-pub trait Hittable {
-    fn hit(&self, ray: &Ray, min: f64, max: f64) -> Option<Record>;
+impl<T: Hittable + ?Sized> Hittable for Box<T> {
+    fn hit(&self, ray: &Ray, min: f64, max: f64) -> Option<Record<'_>> {
+        self.as_ref().hit(ray, min, max)
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        self.as_ref().bounding_box()
+    }
+
+    fn pdf_value(&self, origin: &Point3, dir: &Vec3) -> f64 {
+        self.as_ref().pdf_value(origin, dir)
+    }
+
+    fn random(&self, origin: &Point3) -> Vec3 {
+        self.as_ref().random(origin)
+    }
+
+    fn hit_surface(&self, ray: &Ray, min: f64, max: f64) -> Option<Record<'_>> {
+        self.as_ref().hit_surface(ray, min, max)
+    }
+
+    fn transmittance(&self, ray: &Ray, min: f64, max: f64) -> f64 {
+        self.as_ref().transmittance(ray, min, max)
+    }
+}
+
+impl<T: Hittable + ?Sized> Hittable for Arc<T> {
+    fn hit(&self, ray: &Ray, min: f64, max: f64) -> Option<Record<'_>> {
+        self.as_ref().hit(ray, min, max)
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        self.as_ref().bounding_box()
+    }
+
+    fn pdf_value(&self, origin: &Point3, dir: &Vec3) -> f64 {
+        self.as_ref().pdf_value(origin, dir)
+    }
+
+    fn random(&self, origin: &Point3) -> Vec3 {
+        self.as_ref().random(origin)
+    }
+
+    fn hit_surface(&self, ray: &Ray, min: f64, max: f64) -> Option<Record<'_>> {
+        self.as_ref().hit_surface(ray, min, max)
+    }
+
+    fn transmittance(&self, ray: &Ray, min: f64, max: f64) -> f64 {
+        self.as_ref().transmittance(ray, min, max)
+    }
 }
 
 pub struct HittableList<T> {
@@ -32,6 +153,31 @@ impl<T> HittableList<T> {
     pub fn add(&mut self, object: T) {
         self.objects.push(object)
     }
+
+    pub fn len(&self) -> usize {
+        self.objects.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.objects.is_empty()
+    }
+}
+
+impl<T> FromIterator<T> for HittableList<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        Self {
+            objects: iter.into_iter().collect(),
+        }
+    }
+}
+
+impl<T> IntoIterator for HittableList<T> {
+    type Item = T;
+    type IntoIter = std::vec::IntoIter<T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.objects.into_iter()
+    }
 }
 
 impl<T> Default for HittableList<T> {
@@ -40,13 +186,17 @@ impl<T> Default for HittableList<T> {
     }
 }
 
-impl<T: Hittable> Hittable for HittableList<T> {
-    fn hit(&self, ray: &Ray, min: f64, max: f64) -> Option<Record> {
+impl<T: Hittable> HittableList<T> {
+    // 用 hit 函数求所有物体中最近的交点
+    fn closest<'a, F>(&'a self, ray: &Ray, min: f64, max: f64, hit: F) -> Option<Record<'a>>
+    where
+        F: Fn(&'a T, &Ray, f64, f64) -> Option<Record<'a>>,
+    {
         let mut closest = max;
         let mut rec = None;
 
         for object in &self.objects {
-            if let Some(hit_rec) = object.hit(ray, min, closest) {
+            if let Some(hit_rec) = hit(object, ray, min, closest) {
                 closest = hit_rec.t;
                 rec = Some(hit_rec);
             }
@@ -55,3 +205,47 @@ impl<T: Hittable> Hittable for HittableList<T> {
         rec
     }
 }
+
+impl<T: Hittable> Hittable for HittableList<T> {
+    fn hit(&self, ray: &Ray, min: f64, max: f64) -> Option<Record<'_>> {
+        self.closest(ray, min, max, |o, r, min, max| o.hit(r, min, max))
+    }
+
+    fn hit_surface(&self, ray: &Ray, min: f64, max: f64) -> Option<Record<'_>> {
+        self.closest(ray, min, max, |o, r, min, max| o.hit_surface(r, min, max))
+    }
+
+    fn transmittance(&self, ray: &Ray, min: f64, max: f64) -> f64 {
+        self.objects
+            .iter()
+            .map(|o| o.transmittance(ray, min, max))
+            .product()
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let mut objects = self.objects.iter();
+        let first = objects.next()?.bounding_box()?;
+        objects.try_fold(first, |acc, object| {
+            Some(Aabb::surrounding(&acc, &object.bounding_box()?))
+        })
+    }
+
+    // 等概率选择其中一个物体采样, 概率密度取平均
+    fn pdf_value(&self, origin: &Point3, dir: &Vec3) -> f64 {
+        if self.objects.is_empty() {
+            return 0.0;
+        }
+
+        let sum: f64 = self.objects.iter().map(|o| o.pdf_value(origin, dir)).sum();
+        sum / self.objects.len() as f64
+    }
+
+    fn random(&self, origin: &Point3) -> Vec3 {
+        if self.objects.is_empty() {
+            return Vec3::new(1.0, 0.0, 0.0);
+        }
+
+        let i = random::with_rng(|rng| rng.gen_range(0..self.objects.len()));
+        self.objects[i].random(origin)
+    }
+}
@@ -1,53 +1,109 @@
-use std::io;
+use std::io::{self, BufWriter};
 use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::{fs::File, io::Write};
 
-use anyhow::{Ok, Result};
+use ::image::{codecs::png::PngEncoder, ColorType, ImageEncoder};
+use anyhow::{bail, Ok, Result};
+use rayon::prelude::*;
 
 use crate::vec3::Color;
 
+// 输出图像格式
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    Ppm, // 文本格式的 PPM (P3)
+    Png,
+}
+
+impl Format {
+    // 根据文件扩展名推断格式, 无法识别时报错
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("ppm") => Ok(Self::Ppm),
+            Some(ext) if ext.eq_ignore_ascii_case("png") => Ok(Self::Png),
+            _ => bail!(
+                "{}: unsupported output format, expect .ppm or .png or use --format",
+                path.display()
+            ),
+        }
+    }
+}
+
 pub struct Image {
-    file: File,
+    file: BufWriter<File>,
+    format: Format,
     width: usize,
     height: usize,
 }
 
 impl Image {
-    pub fn open<P: AsRef<Path>>(path: P, width: usize, height: usize) -> Result<Self> {
-        let mut file = File::options().create(true).write(true).open(path)?;
-        file.write_fmt(format_args!("P3\n{} {}\n255\n", width, height))?;
+    pub fn open<P: AsRef<Path>>(
+        path: P,
+        format: Format,
+        width: usize,
+        height: usize,
+    ) -> Result<Self> {
+        let file = BufWriter::new(File::create(path)?);
 
         Ok(Image {
             file,
+            format,
             width,
             height,
         })
     }
 
-    pub fn write_color(&mut self, c: Color, sample: usize) -> Result<()> {
+    // 对采样结果取平均, 再做 gamma 2 校正
+    fn to_rgb(c: Color, sample: usize) -> [u8; 3] {
         let scale = 1.0 / (sample as f64);
-        let r = (c.x() * scale).sqrt().max(0.0).min(0.999) * 256.0;
-        let g = (c.y() * scale).sqrt().max(0.0).min(0.999) * 256.0;
-        let b = (c.z() * scale).sqrt().max(0.0).min(0.999) * 256.0;
-
-        self.file
-            .write_fmt(format_args!("{} {} {}\n", r as u8, g as u8, b as u8))
-            .map_err(|e| e.into())
+        let r = (c.x() * scale).sqrt().clamp(0.0, 0.999) * 256.0;
+        let g = (c.y() * scale).sqrt().clamp(0.0, 0.999) * 256.0;
+        let b = (c.z() * scale).sqrt().clamp(0.0, 0.999) * 256.0;
+        [r as u8, g as u8, b as u8]
     }
 
-    pub fn write_color_with<F>(&mut self, sample: usize, mut f: F) -> Result<()>
+    // 按行并行计算每个像素的颜色, 全部算完之后再按从上到下的顺序写入文件
+    pub fn write_color_with<F>(&mut self, sample: usize, f: F) -> Result<()>
     where
-        F: FnMut(usize, usize) -> Color,
+        F: Fn(usize, usize) -> Color + Sync,
     {
-        let mut stdout = io::stdout().lock();
-        for j in (0..self.height).rev() {
-            stdout.write_fmt(format_args!("\rwritting line {}/{} ...", j, self.height))?;
-            stdout.flush()?;
-            for i in 0..self.width {
-                self.write_color(f(i, j), sample)?;
+        let done = AtomicUsize::new(0);
+        let rows = (0..self.height)
+            .into_par_iter()
+            .rev()
+            .map(|j| {
+                let row = (0..self.width).map(|i| f(i, j)).collect::<Vec<_>>();
+
+                let n = done.fetch_add(1, Ordering::Relaxed) + 1;
+                let mut stdout = io::stdout().lock();
+                stdout.write_fmt(format_args!("\rwritting line {}/{} ...", n, self.height))?;
+                stdout.flush()?;
+
+                Ok(row)
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let pixels = rows.into_iter().flatten().map(|c| Self::to_rgb(c, sample));
+
+        match self.format {
+            Format::Ppm => {
+                let (w, h) = (self.width, self.height);
+                self.file
+                    .write_fmt(format_args!("P3\n{} {}\n255\n", w, h))?;
+                for [r, g, b] in pixels {
+                    self.file.write_fmt(format_args!("{} {} {}\n", r, g, b))?;
+                }
+            }
+            Format::Png => {
+                let buf = pixels.flatten().collect::<Vec<_>>();
+                let (w, h) = (self.width as u32, self.height as u32);
+                PngEncoder::new(&mut self.file).write_image(&buf, w, h, ColorType::Rgb8)?;
             }
         }
 
+        let mut stdout = io::stdout().lock();
         stdout.write_fmt(format_args!("\rdone!\n"))?;
         stdout.flush()?;
 
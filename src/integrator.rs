@@ -0,0 +1,215 @@
+use crate::{
+    hit::{Hittable, Record},
+    pdf::{HittablePdf, Pdf},
+    random,
+    ray::Ray,
+    scene::{Background, Settings},
+    spectrum::Wavelengths,
+    vec3::{Color, Vec3},
+};
+
+// 迭代的路径追踪器
+// 沿路径累计吞吐量 (各次散射衰减的乘积), 超过 min_depth 后用俄罗斯轮盘赌随机终止路径,
+// 存活的路径按存活概率放大吞吐量, 这样提前终止不会让结果偏暗
+#[derive(Clone, Copy, Debug)]
+pub struct PathTracer {
+    pub min_depth: usize, // 从第几次反弹开始做俄罗斯轮盘赌
+    pub max_depth: usize, // 反弹次数的上限, 只是防止路径无限长的保险
+    pub spectral: bool,   // 按波长而不是 RGB 追踪光线
+}
+
+impl PathTracer {
+    pub fn new(min_depth: usize, max_depth: usize) -> Self {
+        Self {
+            min_depth,
+            max_depth,
+            spectral: false,
+        }
+    }
+
+    pub fn with_spectral(self, spectral: bool) -> Self {
+        Self { spectral, ..self }
+    }
+
+    pub fn from_settings(settings: &Settings) -> Self {
+        Self::new(settings.min_depth, settings.max_depth).with_spectral(settings.spectral)
+    }
+
+    // 返回线性 sRGB 颜色
+    // 光谱模式下每条路径随机选一组波长, 材质和光源的 RGB 颜色上采样为这组波长上的值,
+    // 最后经过 CIE XYZ 转换回 sRGB
+    pub fn trace<H, L>(&self, ray: Ray, world: &H, lights: &L, background: &Background) -> Color
+    where
+        H: Hittable + ?Sized,
+        L: Hittable + ?Sized,
+    {
+        if !self.spectral {
+            return self.radiance(ray, world, lights, background, &mut None);
+        }
+
+        let wavelengths = Wavelengths::sample();
+        let ray = ray.with_wavelength(wavelengths.hero());
+        let mut wavelengths = Some(wavelengths);
+        let values = self.radiance(ray, world, lights, background, &mut wavelengths);
+        wavelengths.map_or(values, |w| w.to_rgb(&values))
+    }
+
+    // lights 中的光源在每个非镜面的撞击点上直接采样, 用阴影光线判断是否被遮挡
+    // 光源采样和 BSDF 采样都能得到光源上的同一个方向, 两者按 power heuristic 加权合并
+    // 穿过有吸收的物体表面时记录进入和离开, 在物体内部走过的距离按 Beer-Lambert 定律衰减
+    // wavelengths 为 None 时计算 RGB, 否则计算这组波长上的值
+    fn radiance<H, L>(
+        &self,
+        mut ray: Ray,
+        world: &H,
+        lights: &L,
+        background: &Background,
+        wavelengths: &mut Option<Wavelengths>,
+    ) -> Color
+    where
+        H: Hittable + ?Sized,
+        L: Hittable + ?Sized,
+    {
+        let mut color = Color::new(0.0, 0.0, 0.0);
+        let mut throughput = Color::new(1.0, 1.0, 1.0);
+        let mut prev_pdf = None; // 上一次散射的概率密度, 镜面散射和相机发出的光线为 None
+        let mut inside: Vec<Color> = vec![]; // 当前所在的各层物体的吸收系数, 最内层在最后
+
+        for depth in 0..self.max_depth {
+            let Some(rec) = world.hit(&ray, 0.001, f64::INFINITY) else {
+                color += throughput * spectrum(wavelengths, background.color(&ray));
+                break;
+            };
+            if let Some(absorption) = inside.last() {
+                throughput =
+                    throughput * transmittance(absorption, rec.t * ray.direction().length());
+            }
+
+            // 非镜面散射后命中光源时, 按 BSDF 采样这一策略的权重计入
+            let emitted = rec.material.emitted(&ray, &rec);
+            if !emitted.near_zero() {
+                let weight = prev_pdf.map_or(1.0, |pdf| {
+                    power_heuristic(pdf, lights.pdf_value(ray.origin(), ray.direction()))
+                });
+                color += weight * throughput * spectrum(wavelengths, emitted);
+            }
+
+            // eval 和 scattering_pdf 已经包含了所有非镜面部分, 光源采样不能只在
+            // 选中非镜面部分时才做, 否则会再乘一次选中的概率
+            if rec.material.has_diffuse() {
+                let direct = Self::direct(&ray, &rec, world, lights, inside.last(), wavelengths);
+                color += throughput * direct;
+            }
+
+            let Some(scatter) = rec.material.scatter(&ray, &rec) else {
+                break;
+            };
+
+            // 色散或干涉后各个波长的散射不再相同, 只有主波长沿这条路径继续
+            if rec.material.wavelength_dependent() {
+                if let Some(w) = wavelengths {
+                    w.terminate_secondary();
+                    throughput = Color::new(throughput.x(), 0.0, 0.0);
+                }
+            }
+
+            // 法线总是朝向入射一侧, 散射方向与法线相反说明光线穿过了表面
+            let absorption = rec.material.absorption();
+            if !absorption.near_zero() && Vec3::dot(scatter.ray.direction(), &rec.normal) < 0.0 {
+                match rec.front_face {
+                    true => inside.push(spectrum(wavelengths, absorption)),
+                    false => {
+                        inside.pop();
+                    }
+                }
+            }
+            // 只有做过光源采样的非镜面方向才需要按 MIS 加权, 选中镜面部分时光源全部计入
+            prev_pdf = (rec.material.has_diffuse() && !scatter.specular).then_some(scatter.pdf);
+            throughput = throughput * spectrum(wavelengths, scatter.attenuation);
+
+            // 吞吐量越小的路径越容易被终止, 存活概率不超过 0.95 保证路径终会结束
+            if depth + 1 >= self.min_depth {
+                let q = throughput
+                    .x()
+                    .max(throughput.y())
+                    .max(throughput.z())
+                    .min(0.95);
+                if random::random_double() >= q {
+                    break;
+                }
+                throughput /= q;
+            }
+
+            ray = Ray {
+                wavelength: ray.wavelength,
+                ..scatter.ray
+            };
+        }
+
+        color
+    }
+
+    // 向光源采样一个方向, 阴影光线第一个撞到的物体发出的光就是这个方向上的直接光照
+    // 撞击点在有吸收的物体内部时, 阴影光线同样按走过的距离衰减
+    fn direct<H, L>(
+        ray: &Ray,
+        rec: &Record,
+        world: &H,
+        lights: &L,
+        absorption: Option<&Color>,
+        wavelengths: &Option<Wavelengths>,
+    ) -> Color
+    where
+        H: Hittable + ?Sized,
+        L: Hittable + ?Sized,
+    {
+        let pdf = HittablePdf::new(lights, rec.p);
+        let dir = pdf.generate();
+        let light_pdf = pdf.value(&dir);
+        if light_pdf <= 0.0 {
+            return Color::default();
+        }
+
+        let f = rec.material.eval(ray, rec, &dir);
+        if f.near_zero() {
+            return Color::default();
+        }
+
+        // 阴影光线穿过参与介质, 只被表面挡住, 介质的衰减用透射率估计
+        let shadow = Ray::new(rec.p, dir, ray.time());
+        let Some(light) = world.hit_surface(&shadow, 0.001, f64::INFINITY) else {
+            return Color::default();
+        };
+
+        let weight = power_heuristic(light_pdf, rec.material.scattering_pdf(ray, rec, &dir));
+        let tr = absorption.map_or(Color::new(1.0, 1.0, 1.0), |a| {
+            transmittance(a, light.t * dir.length())
+        }) * world.transmittance(&shadow, 0.001, light.t);
+        let emitted = spectrum(wavelengths, light.material.emitted(&shadow, &light));
+        weight / light_pdf * spectrum(wavelengths, f) * tr * emitted
+    }
+}
+
+// 光谱模式下把 RGB 颜色换成这组波长上的值
+fn spectrum(wavelengths: &Option<Wavelengths>, rgb: Color) -> Color {
+    wavelengths.map_or(rgb, |w| w.upsample(&rgb))
+}
+
+// 吸收系数为 absorption 的介质中走过 distance 后剩余的比例
+fn transmittance(absorption: &Color, distance: f64) -> Color {
+    Color::new(
+        (-absorption.x() * distance).exp(),
+        (-absorption.y() * distance).exp(),
+        (-absorption.z() * distance).exp(),
+    )
+}
+
+// 多重重要性采样的 power heuristic (β = 2), 返回概率密度为 pdf 的策略的权重
+fn power_heuristic(pdf: f64, other: f64) -> f64 {
+    if other <= 0.0 {
+        return 1.0;
+    }
+
+    let (a, b) = (pdf * pdf, other * other);
+    a / (a + b)
+}
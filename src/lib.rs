@@ -1,7 +1,26 @@
+pub mod aabb;
+pub mod bvh;
 pub mod camera;
+pub mod film;
 pub mod hit;
 pub mod image;
+pub mod integrator;
+pub mod mat4;
 pub mod material;
+pub mod medium;
+pub mod mesh;
+pub mod microfacet;
+pub mod obj;
+pub mod pdf;
+pub mod perlin;
+pub mod plane;
+pub mod quad;
+pub mod random;
 pub mod ray;
+pub mod scene;
+pub mod spectrum;
 pub mod sphere;
+pub mod texture;
+pub mod transform;
+pub mod triangle;
 pub mod vec3;
@@ -1,124 +1,327 @@
-#![feature(portable_simd)]
-use std::rc::Rc;
+use std::path::PathBuf;
+use std::sync::Arc;
 
-use anyhow::Result;
-use rand::Rng;
-// use rayon::prelude::*;
-// use std::simd::Simd;
+use anyhow::{bail, Result};
+use clap::{builder::RangedU64ValueParser, Parser, ValueEnum};
 
 use rtwk::{
-    camera::Camera,
     hit::Hittable,
     hit::HittableList,
-    image::Image,
-    material::{Dielectric, Lambertian, Material, Metal},
-    ray::Ray,
-    sphere::Sphere,
+    image::{Format, Image},
+    integrator::PathTracer,
+    material::{Dielectric, DiffuseLight, Lambertian, Material, Metal},
+    medium::ConstantMedium,
+    plane::Plane,
+    quad::{BoxShape, Quad},
+    random,
+    scene::{Background, CameraSettings, Scene, Settings},
+    sphere::{MovingSphere, Sphere},
+    transform::Transform,
     vec3::Point3,
     vec3::{Color, Vec3},
 };
 
+/// Ray Tracing in One Weekend, in Rust
+#[derive(Parser)]
+#[command(version, about)]
+struct Cli {
+    /// Scene description file (TOML), see scenes/ for examples
+    scene: Option<PathBuf>,
+
+    /// Built-in scene to render when no scene file is given
+    #[arg(short, long, value_enum, default_value_t = Preset::Random)]
+    preset: Preset,
+
+    /// Image width in pixels
+    #[arg(short = 'W', long, value_parser = size())]
+    width: Option<usize>,
+
+    /// Image height in pixels, derived from width and aspect ratio by default
+    #[arg(short = 'H', long, value_parser = size())]
+    height: Option<usize>,
+
+    /// Camera aspect ratio (width / height)
+    #[arg(short, long, value_parser = positive)]
+    aspect_ratio: Option<f64>,
+
+    /// Samples per pixel
+    #[arg(short, long, value_parser = RangedU64ValueParser::<usize>::new().range(1..))]
+    samples_per_pixel: Option<usize>,
+
+    /// Number of bounces before paths start being terminated by Russian roulette
+    #[arg(long)]
+    min_depth: Option<usize>,
+
+    /// Maximum number of ray bounces
+    #[arg(short = 'd', long)]
+    max_depth: Option<usize>,
+
+    /// Output image path
+    #[arg(short, long, default_value = "output.ppm")]
+    output: PathBuf,
+
+    /// Output image format, inferred from the output extension by default
+    #[arg(short, long, value_enum)]
+    format: Option<OutputFormat>,
+
+    /// Trace wavelengths instead of RGB, needed for dispersion
+    #[arg(long)]
+    spectral: bool,
+
+    /// Random number seed, the same seed always gives the same image
+    #[arg(long)]
+    seed: Option<u64>,
+
+    /// Number of render threads, defaults to the number of CPUs
+    #[arg(short = 'j', long)]
+    threads: Option<usize>,
+}
+
+// 图像的宽高在 [2, Settings::MAX_SIZE] 之间
+fn size() -> RangedU64ValueParser<usize> {
+    RangedU64ValueParser::new().range(2..=Settings::MAX_SIZE as u64)
+}
+
+// 长宽比必须是有限的正数
+fn positive(s: &str) -> Result<f64> {
+    let value: f64 = s.parse()?;
+    if !(value.is_finite() && value > 0.0) {
+        bail!("{value} is not a positive number");
+    }
+    Ok(value)
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum OutputFormat {
+    /// Plain text PPM (P3)
+    Ppm,
+    /// PNG
+    Png,
+}
+
+impl From<OutputFormat> for Format {
+    fn from(format: OutputFormat) -> Self {
+        match format {
+            OutputFormat::Ppm => Format::Ppm,
+            OutputFormat::Png => Format::Png,
+        }
+    }
+}
+
 fn main() -> Result<()> {
-    // image
-    let aspect_ratio = 3.0 / 2.0;
-    let image_width = 1200;
-    let image_height = (image_width as f64 / aspect_ratio) as usize;
-    let mut img = Image::open("output.ppm", image_width, image_height)?;
+    let cli = Cli::parse();
 
-    let cam = new_camera(aspect_ratio);
-    let world = random_scene();
+    if let Some(threads) = cli.threads {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build_global()?;
+    }
 
-    // 光线反射深度
-    let max_depth = 50;
+    // 指定了场景文件时从文件读取, 否则渲染内置场景
+    let mut scene = match &cli.scene {
+        Some(path) => Scene::load(path)?,
+        None => cli.preset.scene(cli.seed.unwrap_or_default()),
+    };
+    cli.apply(&mut scene)?;
 
-    // 多重采样抗锯齿
-    let samples_per_pixel = 500;
+    let Scene {
+        camera,
+        world,
+        lights,
+        background,
+        settings,
+    } = scene;
+    let cam = camera.camera();
+
+    // image
+    let image_width = settings.width;
+    let image_height = settings.height;
+    let format = match cli.format {
+        Some(format) => Format::from(format),
+        None => Format::from_path(&cli.output)?,
+    };
+    let mut img = Image::open(&cli.output, format, image_width, image_height)?;
+
+    let Settings {
+        samples_per_pixel, // 多重采样抗锯齿
+        seed,              // 随机数种子, 相同的种子渲染出相同的图像
+        ..
+    } = settings;
+    let tracer = PathTracer::from_settings(&settings);
 
     img.write_color_with(samples_per_pixel, |i, j| {
-        let mut r = rand::thread_rng();
+        // 每个像素使用独立的随机数流, 结果与渲染线程的调度顺序无关
+        random::reseed(seed, (j * image_width + i) as u64 + 1);
         (0..samples_per_pixel).fold(Color::new(0.0, 0.0, 0.0), |acc, _| {
-            let u = (i as f64 + r.gen_range(0.0..1.0)) / (image_width - 1) as f64;
-            let v = (j as f64 + r.gen_range(0.0..1.0)) / (image_height - 1) as f64;
+            let u = (i as f64 + random::random_double()) / (image_width - 1) as f64;
+            let v = (j as f64 + random::random_double()) / (image_height - 1) as f64;
             let r = cam.ray(u, v);
-            acc + ray_color(&r, &world, max_depth)
+            acc + tracer.trace(r, &world, &lights, &background)
         })
-
-        // FIXME: illegal hardware instruction
-        // let color = (0..samples_per_pixel)
-        //     .into_par_iter()
-        //     .map(|_| {
-        //         let mut r = rand::thread_rng();
-        //         let u = (i as f64 + r.gen_range(0.0..1.0)) / (image_width - 1) as f64;
-        //         let v = (j as f64 + r.gen_range(0.0..1.0)) / (image_height - 1) as f64;
-        //         let r = cam.ray(u, v);
-        //         let color = ray_color(&r, &world, max_depth);
-        //         Simd::from_array([color.x(), color.y(), color.z(), 0.0])
-        //     })
-        //     .sum::<Simd<f64, 4>>()
-        //     .to_array();
-
-        // Color::new(color[0], color[1], color[2])
     })
 }
 
-fn ray_color<H: Hittable>(r: &Ray, world: &H, depth: usize) -> Color {
-    if depth == 0 {
-        return Color::new(0.0, 0.0, 0.0);
-    }
+impl Cli {
+    // 用命令行参数覆盖场景中的渲染参数
+    // 参数合并进场景设置后再检查一遍, 场景文件中的值和推算出的宽高也可能不合法
+    fn apply(&self, scene: &mut Scene) -> Result<()> {
+        let settings = &mut scene.settings;
+        let camera = &mut scene.camera;
+
+        // 同时指定了宽和高时用它们的比例作为长宽比
+        match (self.width, self.height, self.aspect_ratio) {
+            (Some(w), Some(h), None) => camera.aspect_ratio = w as f64 / h as f64,
+            (_, _, Some(aspect_ratio)) => camera.aspect_ratio = aspect_ratio,
+            _ => {}
+        }
 
-    // 命中物体后在对应材质上散射
-    if let Some(rec) = world.hit(r, 0.001, f64::MAX) {
-        return match rec.material.as_ref().scatter(r, &rec) {
-            Some((scattered, attenuation)) => attenuation * ray_color(&scattered, world, depth - 1),
-            None => Color::new(0.0, 0.0, 0.0),
+        let aspect_ratio = camera.aspect_ratio;
+        if !(aspect_ratio.is_finite() && aspect_ratio > 0.0) {
+            bail!("aspect ratio {} is not a positive number", aspect_ratio);
+        }
+        (settings.width, settings.height) = match (self.width, self.height) {
+            (Some(w), Some(h)) => (w, h),
+            (Some(w), None) => (w, (w as f64 / aspect_ratio) as usize),
+            (None, Some(h)) => ((h as f64 * aspect_ratio) as usize, h),
+            (None, None) => {
+                let w = settings.width;
+                (w, (w as f64 / aspect_ratio) as usize)
+            }
         };
+
+        if let Some(samples_per_pixel) = self.samples_per_pixel {
+            settings.samples_per_pixel = samples_per_pixel;
+        }
+        if let Some(min_depth) = self.min_depth {
+            settings.min_depth = min_depth;
+        }
+        if let Some(max_depth) = self.max_depth {
+            settings.max_depth = max_depth;
+        }
+        if let Some(seed) = self.seed {
+            settings.seed = seed;
+        }
+        if self.spectral {
+            settings.spectral = true;
+        }
+
+        // 宽高由长宽比推算时可能超出范围, 浮点数转换成 usize 会饱和而不是溢出
+        let range = 2..=Settings::MAX_SIZE;
+        if !range.contains(&settings.width) || !range.contains(&settings.height) {
+            bail!(
+                "image size {}x{} is out of range, width and height must be in [2, {}]",
+                settings.width,
+                settings.height,
+                Settings::MAX_SIZE
+            );
+        }
+        if settings.samples_per_pixel == 0 {
+            bail!("samples per pixel must be at least 1");
+        }
+        Ok(())
     }
+}
+
+// 场景中的物体列表
+type Objects = HittableList<Arc<dyn Hittable>>;
+
+// 内置场景
+#[derive(Clone, Copy, ValueEnum)]
+enum Preset {
+    /// Final scene of "Ray Tracing in One Weekend", ~480 random spheres
+    Random,
+    /// The random scene with motion-blurred bouncing diffuse spheres
+    BouncingSpheres,
+    /// Cornell box lit only by the area light on its ceiling
+    CornellBox,
+    /// Cornell box with its two blocks replaced by black and white smoke
+    CornellSmoke,
+}
+
+impl Preset {
+    fn scene(self, seed: u64) -> Scene {
+        // 场景中的随机数也由种子决定
+        random::reseed(seed, 0);
+        let settings = Settings {
+            seed,
+            ..Settings::default()
+        };
 
-    let unit = r.direction().unit();
-    let t = 0.5 * (unit.y() + 1.0);
-    (1.0 - t) * Color::new(1.0, 1.0, 1.0) + t * Color::new(0.5, 0.7, 1.0)
+        match self {
+            Self::Random => Scene {
+                camera: new_camera(settings.width as f64 / settings.height as f64),
+                world: Scene::world(random_scene(false)),
+                lights: HittableList::new(),
+                background: Background::default(),
+                settings,
+            },
+            Self::BouncingSpheres => Scene {
+                camera: CameraSettings {
+                    shutter: (0.0, 1.0),
+                    ..new_camera(settings.width as f64 / settings.height as f64)
+                },
+                world: Scene::world(random_scene(true)),
+                lights: HittableList::new(),
+                background: Background::default(),
+                settings,
+            },
+            Self::CornellBox | Self::CornellSmoke => {
+                let (world, lights) = cornell_box(matches!(self, Self::CornellSmoke));
+                Scene {
+                    camera: cornell_box_camera(),
+                    world: Scene::world(world),
+                    lights,
+                    background: Background::Solid(Color::new(0.0, 0.0, 0.0)),
+                    settings: Settings {
+                        width: 600,
+                        height: 600,
+                        samples_per_pixel: 200,
+                        ..settings
+                    },
+                }
+            }
+        }
+    }
 }
 
-fn new_camera(aspect_ratio: f64) -> Camera {
-    let lookfrom = Point3::new(13.0, 2.0, 3.0);
-    let lookat = Point3::new(0.0, 0.0, 0.0);
-    let vup = Vec3::new(0.0, 1.0, 0.0);
-    let vfov = 20.0;
-    let aperture = 0.1;
-    let dist_to_focus = 10.0;
-
-    Camera::new(
-        lookfrom,
-        lookat,
-        vup,
-        vfov,
+fn new_camera(aspect_ratio: f64) -> CameraSettings {
+    CameraSettings {
+        lookfrom: Point3::new(13.0, 2.0, 3.0),
+        lookat: Point3::new(0.0, 0.0, 0.0),
+        vup: Vec3::new(0.0, 1.0, 0.0),
+        vfov: 20.0,
         aspect_ratio,
-        aperture,
-        dist_to_focus,
-    )
+        aperture: 0.1,
+        focus_dist: 10.0,
+        shutter: (0.0, 0.0),
+    }
 }
 
-fn random_scene() -> HittableList<Sphere> {
-    let mut world = HittableList::new();
+// bouncing 为 true 时漫反射小球在快门打开期间向上弹起
+fn random_scene(bouncing: bool) -> HittableList<Arc<dyn Hittable>> {
+    let mut world: HittableList<Arc<dyn Hittable>> = HittableList::new();
 
-    let ground_material = Rc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
-    let ground = Sphere::new(Point3::new(0.0, -1000.0, 0.0), 1000.0, ground_material);
-    world.add(ground);
+    let ground_material = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+    let ground = Plane::new(
+        Point3::new(0.0, 0.0, 0.0),
+        Vec3::new(0.0, 1.0, 0.0),
+        ground_material,
+    );
+    world.add(Arc::new(ground));
 
-    let mat1 = Rc::new(Dielectric::new(1.5));
+    let mat1 = Arc::new(Dielectric::new(1.5));
     let sp1 = Sphere::new(Point3::new(0.0, 1.0, 0.0), 1.0, mat1);
-    world.add(sp1);
+    world.add(Arc::new(sp1));
 
-    let mat2 = Rc::new(Lambertian::new(Color::new(0.4, 0.2, 0.1)));
+    let mat2 = Arc::new(Lambertian::new(Color::new(0.4, 0.2, 0.1)));
     let sp2 = Sphere::new(Point3::new(-4.0, 1.0, 0.0), 1.0, mat2);
-    world.add(sp2);
+    world.add(Arc::new(sp2));
 
-    let mat3 = Rc::new(Metal::new(Color::new(0.7, 0.6, 0.5), 0.0));
+    let mat3 = Arc::new(Metal::new(Color::new(0.7, 0.6, 0.5), 0.0));
     let sp3 = Sphere::new(Point3::new(4.0, 1.0, 0.0), 1.0, mat3);
-    world.add(sp3);
+    world.add(Arc::new(sp3));
 
-    let mut rng = rand::thread_rng();
-    let mut rand_double = || rng.gen_range(0.0..1.0);
+    let rand_double = random::random_double;
 
     for a in -11..11 {
         for b in -11..11 {
@@ -128,30 +331,122 @@ fn random_scene() -> HittableList<Sphere> {
 
             let p = Point3::new(4.0, 0.2, 0.0);
             if (center - p).length() > 0.9 {
-                let material: Rc<dyn Material> = match rand_double() {
+                let n = rand_double();
+                let material: Arc<dyn Material> = match n {
                     // 漫反射材质
                     n if (0.0..0.60).contains(&n) => {
                         let c1 = Color::random();
                         let c2 = Color::random();
                         let albedo = c1 * c2;
-                        Rc::new(Lambertian::new(albedo))
+                        Arc::new(Lambertian::new(albedo))
                     }
                     // 金属材质
                     n if (0.60..0.85).contains(&n) => {
                         let albedo = Color::random_range(0.5, 1.0);
                         let fuzz = rand_double() / 2.0;
-                        Rc::new(Metal::new(albedo, fuzz))
+                        Arc::new(Metal::new(albedo, fuzz))
                     }
                     // 玻璃材质
-                    n if (0.85..1.0).contains(&n) => Rc::new(Dielectric::new(1.5)),
+                    n if (0.85..1.0).contains(&n) => Arc::new(Dielectric::new(1.5)),
                     _ => unreachable!(),
                 };
 
                 let radius = 0.2;
-                world.add(Sphere::new(center, radius, material));
+                if bouncing && n < 0.6 {
+                    let center1 = center + Vec3::new(0.0, 0.5 * rand_double(), 0.0);
+                    let sphere = MovingSphere::new(center, center1, 0.0, 1.0, radius, material);
+                    world.add(Arc::new(sphere));
+                } else {
+                    world.add(Arc::new(Sphere::new(center, radius, material)));
+                }
             }
         }
     }
 
     world
 }
+
+fn cornell_box_camera() -> CameraSettings {
+    CameraSettings {
+        lookfrom: Point3::new(278.0, 278.0, -800.0),
+        lookat: Point3::new(278.0, 278.0, 0.0),
+        vup: Vec3::new(0.0, 1.0, 0.0),
+        vfov: 40.0,
+        aspect_ratio: 1.0,
+        aperture: 0.0,
+        focus_dist: 10.0,
+        shutter: (0.0, 0.0),
+    }
+}
+
+// smoke 为 true 时两个长方体换成黑白两团烟雾
+fn cornell_box(smoke: bool) -> (Objects, Objects) {
+    let mut world: HittableList<Arc<dyn Hittable>> = HittableList::new();
+
+    let red: Arc<dyn Material> = Arc::new(Lambertian::new(Color::new(0.65, 0.05, 0.05)));
+    let white: Arc<dyn Material> = Arc::new(Lambertian::new(Color::new(0.73, 0.73, 0.73)));
+    let green: Arc<dyn Material> = Arc::new(Lambertian::new(Color::new(0.12, 0.45, 0.15)));
+    let light: Arc<dyn Material> = Arc::new(DiffuseLight::new(Color::new(15.0, 15.0, 15.0)));
+
+    let mut quad = |q: Point3, u: Vec3, v: Vec3, material: &Arc<dyn Material>| {
+        world.add(Arc::new(Quad::new(q, u, v, material.clone())));
+    };
+
+    let (x, y, z) = (
+        Vec3::new(555.0, 0.0, 0.0),
+        Vec3::new(0.0, 555.0, 0.0),
+        Vec3::new(0.0, 0.0, 555.0),
+    );
+    quad(Point3::new(555.0, 0.0, 0.0), y, z, &green);
+    quad(Point3::new(0.0, 0.0, 0.0), y, z, &red);
+    quad(Point3::new(0.0, 0.0, 0.0), x, z, &white);
+    quad(Point3::new(555.0, 555.0, 555.0), -x, -z, &white);
+    quad(Point3::new(0.0, 0.0, 555.0), x, y, &white);
+
+    // 顶部的光源同时放进光源列表用于直接光照
+    let light_u = Vec3::new(-130.0, 0.0, 0.0);
+    let light_v = Vec3::new(0.0, 0.0, -105.0);
+    let light: Arc<dyn Hittable> = Arc::new(Quad::new(
+        Point3::new(343.0, 554.0, 332.0),
+        light_u,
+        light_v,
+        light,
+    ));
+    world.add(light.clone());
+    let lights = HittableList::from_iter([light]);
+
+    // 两个绕 y 轴旋转过的长方体
+    let tall = BoxShape::new(
+        Point3::default(),
+        Point3::new(165.0, 330.0, 165.0),
+        white.clone(),
+    );
+    let tall = Transform::translate(
+        Transform::rotate_y(tall, 15.0),
+        Vec3::new(265.0, 0.0, 295.0),
+    );
+
+    let short = BoxShape::new(Point3::default(), Point3::new(165.0, 165.0, 165.0), white);
+    let short = Transform::translate(
+        Transform::rotate_y(short, -18.0),
+        Vec3::new(130.0, 0.0, 65.0),
+    );
+
+    if smoke {
+        world.add(Arc::new(ConstantMedium::new(
+            tall,
+            0.01,
+            Color::new(0.0, 0.0, 0.0),
+        )));
+        world.add(Arc::new(ConstantMedium::new(
+            short,
+            0.01,
+            Color::new(1.0, 1.0, 1.0),
+        )));
+    } else {
+        world.add(Arc::new(tall));
+        world.add(Arc::new(short));
+    }
+
+    (world, lights)
+}
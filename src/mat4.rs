@@ -0,0 +1,219 @@
+use std::ops::Mul;
+
+use crate::vec3::{Point3, Vec3};
+
+// 4x4 矩阵, 按行存储, 用于仿射变换 (平移/旋转/缩放)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Mat4(pub [[f64; 4]; 4]);
+
+impl Mat4 {
+    pub fn identity() -> Self {
+        let mut m = [[0.0; 4]; 4];
+        for (i, row) in m.iter_mut().enumerate() {
+            row[i] = 1.0;
+        }
+        Self(m)
+    }
+
+    pub fn translate(offset: Vec3) -> Self {
+        let mut m = Self::identity();
+        m.0[0][3] = offset.x();
+        m.0[1][3] = offset.y();
+        m.0[2][3] = offset.z();
+        m
+    }
+
+    pub fn scale(s: Vec3) -> Self {
+        let mut m = Self::identity();
+        m.0[0][0] = s.x();
+        m.0[1][1] = s.y();
+        m.0[2][2] = s.z();
+        m
+    }
+
+    // 绕任意轴旋转 degrees 度, 罗德里格斯旋转公式
+    pub fn rotate(axis: Vec3, degrees: f64) -> Self {
+        let a = axis.unit();
+        let (sin, cos) = degrees.to_radians().sin_cos();
+        let (x, y, z) = (a.x(), a.y(), a.z());
+        let t = 1.0 - cos;
+
+        Self([
+            [
+                t * x * x + cos,
+                t * x * y - sin * z,
+                t * x * z + sin * y,
+                0.0,
+            ],
+            [
+                t * x * y + sin * z,
+                t * y * y + cos,
+                t * y * z - sin * x,
+                0.0,
+            ],
+            [
+                t * x * z - sin * y,
+                t * y * z + sin * x,
+                t * z * z + cos,
+                0.0,
+            ],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    pub fn rotate_x(degrees: f64) -> Self {
+        Self::rotate(Vec3::new(1.0, 0.0, 0.0), degrees)
+    }
+
+    pub fn rotate_y(degrees: f64) -> Self {
+        Self::rotate(Vec3::new(0.0, 1.0, 0.0), degrees)
+    }
+
+    pub fn rotate_z(degrees: f64) -> Self {
+        Self::rotate(Vec3::new(0.0, 0.0, 1.0), degrees)
+    }
+
+    pub fn transpose(&self) -> Self {
+        let mut m = [[0.0; 4]; 4];
+        for (i, row) in m.iter_mut().enumerate() {
+            for (j, v) in row.iter_mut().enumerate() {
+                *v = self.0[j][i];
+            }
+        }
+        Self(m)
+    }
+
+    // 高斯-约旦消元求逆矩阵, 矩阵不可逆时返回 None
+    pub fn inverse(&self) -> Option<Self> {
+        let mut a = self.0;
+        let mut inv = Self::identity().0;
+
+        for col in 0..4 {
+            // 选当前列绝对值最大的行作为主元, 减小误差
+            let pivot = (col..4).max_by(|&i, &j| a[i][col].abs().total_cmp(&a[j][col].abs()))?;
+            if a[pivot][col].abs() < 1e-12 {
+                return None;
+            }
+            a.swap(col, pivot);
+            inv.swap(col, pivot);
+
+            let p = a[col][col];
+            for j in 0..4 {
+                a[col][j] /= p;
+                inv[col][j] /= p;
+            }
+
+            for row in 0..4 {
+                if row == col {
+                    continue;
+                }
+                let f = a[row][col];
+                for j in 0..4 {
+                    a[row][j] -= f * a[col][j];
+                    inv[row][j] -= f * inv[col][j];
+                }
+            }
+        }
+
+        Some(Self(inv))
+    }
+
+    // 变换点, 会受到平移的影响
+    pub fn transform_point(&self, p: &Point3) -> Point3 {
+        let m = &self.0;
+        Point3::new(
+            m[0][0] * p.x() + m[0][1] * p.y() + m[0][2] * p.z() + m[0][3],
+            m[1][0] * p.x() + m[1][1] * p.y() + m[1][2] * p.z() + m[1][3],
+            m[2][0] * p.x() + m[2][1] * p.y() + m[2][2] * p.z() + m[2][3],
+        )
+    }
+
+    // 变换方向向量, 不受平移的影响
+    pub fn transform_vector(&self, v: &Vec3) -> Vec3 {
+        let m = &self.0;
+        Vec3::new(
+            m[0][0] * v.x() + m[0][1] * v.y() + m[0][2] * v.z(),
+            m[1][0] * v.x() + m[1][1] * v.y() + m[1][2] * v.z(),
+            m[2][0] * v.x() + m[2][1] * v.y() + m[2][2] * v.z(),
+        )
+    }
+}
+
+impl Default for Mat4 {
+    fn default() -> Self {
+        Self::identity()
+    }
+}
+
+impl Mul for Mat4 {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self::Output {
+        let mut m = [[0.0; 4]; 4];
+        for (i, row) in m.iter_mut().enumerate() {
+            for (j, v) in row.iter_mut().enumerate() {
+                *v = (0..4).map(|k| self.0[i][k] * rhs.0[k][j]).sum();
+            }
+        }
+        Self(m)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_near(a: &Mat4, b: &Mat4) {
+        for i in 0..4 {
+            for j in 0..4 {
+                assert!((a.0[i][j] - b.0[i][j]).abs() < 1e-9, "{:?} != {:?}", a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn inverse() {
+        let matrices = [
+            Mat4::identity(),
+            Mat4::translate(Vec3::new(1.0, -2.0, 3.0)),
+            Mat4::scale(Vec3::new(2.0, 0.5, -3.0)),
+            Mat4::rotate(Vec3::new(1.0, 2.0, 3.0), 37.0),
+            Mat4::translate(Vec3::new(4.0, 5.0, 6.0))
+                * Mat4::rotate_z(10.0)
+                * Mat4::rotate_y(-20.0)
+                * Mat4::rotate_x(30.0)
+                * Mat4::scale(Vec3::new(0.1, 7.0, 2.0)),
+            // 对角线上为 0, 需要选主元
+            Mat4([
+                [0.0, 1.0, 0.0, 1.0],
+                [1.0, 0.0, 0.0, 2.0],
+                [0.0, 0.0, 1.0, 3.0],
+                [0.0, 0.0, 0.0, 1.0],
+            ]),
+        ];
+        for m in matrices {
+            let inv = m.inverse().unwrap();
+            assert_near(&(m * inv), &Mat4::identity());
+            assert_near(&(inv * m), &Mat4::identity());
+        }
+    }
+
+    #[test]
+    fn known_inverse() {
+        let offset = Vec3::new(1.0, 2.0, 3.0);
+        assert_near(
+            &Mat4::translate(offset).inverse().unwrap(),
+            &Mat4::translate(-offset),
+        );
+        assert_near(
+            &Mat4::rotate_y(25.0).inverse().unwrap(),
+            &Mat4::rotate_y(-25.0),
+        );
+    }
+
+    #[test]
+    fn singular() {
+        assert!(Mat4::scale(Vec3::new(1.0, 0.0, 1.0)).inverse().is_none());
+        assert!(Mat4([[0.0; 4]; 4]).inverse().is_none());
+    }
+}
@@ -1,71 +1,274 @@
-use rand::Rng;
+use std::f64::consts::PI;
+use std::sync::Arc;
 
 use crate::{
+    film::{Film, FilmBase},
     hit::Record,
+    microfacet::{self, fresnel_dielectric, fresnel_schlick, Ggx},
+    pdf::{CosinePdf, Pdf, SpherePdf},
+    random,
     ray::Ray,
-    vec3::{Color, Vec3},
+    texture::{SolidColor, Texture},
+    vec3::{Color, Onb, Point3, Vec3},
 };
 
-pub trait Material {
+// 一次散射的结果
+pub struct Scatter {
+    pub ray: Ray,
+    pub attenuation: Color, // BSDF 乘以余弦项再除以 pdf
+    pub pdf: f64,           // 采样到 ray 方向的概率密度, 镜面散射时没有意义
+    pub specular: bool,     // 镜面散射只有固定的方向, 无法和光源采样结合
+}
+
+impl Scatter {
+    pub fn specular(ray: Ray, attenuation: Color) -> Self {
+        Self {
+            ray,
+            attenuation,
+            pdf: 0.0,
+            specular: true,
+        }
+    }
+}
+
+pub trait Material: Send + Sync {
     // 材质的散射
-    fn scatter(&self, rin: &Ray, rec: &Record) -> Option<(Ray, Color)>;
+    fn scatter(&self, rin: &Ray, rec: &Record) -> Option<Scatter>;
+
+    // 材质自身发出的光, 默认不发光
+    fn emitted(&self, _rin: &Ray, _rec: &Record) -> Color {
+        Color::default()
+    }
+
+    // 光线从 dir 方向射入后向 rin 反方向散射的 BSDF 乘以余弦项, 只包含非镜面的部分
+    fn eval(&self, _rin: &Ray, _rec: &Record, _dir: &Vec3) -> Color {
+        Color::default()
+    }
+
+    // scatter 采样到 dir 方向的概率密度, 与 eval 一起用于多重重要性采样
+    fn scattering_pdf(&self, _rin: &Ray, _rec: &Record, _dir: &Vec3) -> f64 {
+        0.0
+    }
+
+    // 封闭物体内部每单位距离的吸收系数, 光线在内部走过距离 d 后按 exp(-absorption * d) 衰减
+    // 默认不吸收, 光线穿过表面进出物体由积分器根据 Record::front_face 判断
+    fn absorption(&self) -> Color {
+        Color::default()
+    }
+
+    // 是否有可以用 eval 计算的非镜面部分, 有的话积分器在这里做光源采样,
+    // 与这一次 scatter 选中的是不是镜面部分无关
+    fn has_diffuse(&self) -> bool {
+        false
+    }
+
+    // 散射是否与波长有关 (色散, 薄膜干涉), 光谱渲染时经过这种材质散射的路径只保留主波长
+    fn wavelength_dependent(&self) -> bool {
+        false
+    }
 }
 
 // 漫反射材质
 pub struct Lambertian {
-    albedo: Color, // 反射率
+    albedo: Arc<dyn Texture>, // 反射率
 }
 
 impl Lambertian {
     pub fn new(albedo: Color) -> Self {
+        Self::with_texture(Arc::new(SolidColor::new(albedo)))
+    }
+
+    pub fn with_texture(albedo: Arc<dyn Texture>) -> Self {
         Self { albedo }
     }
 }
 
 impl Material for Lambertian {
-    fn scatter(&self, _: &Ray, rec: &Record) -> Option<(Ray, Color)> {
-        let mut scatter_dir = rec.normal + Vec3::random_unit_vector();
-        if scatter_dir.near_zero() {
-            scatter_dir = rec.normal;
+    // 按余弦分布重要性采样, BRDF 乘以余弦项再除以 pdf 正好是 albedo
+    fn scatter(&self, rin: &Ray, rec: &Record) -> Option<Scatter> {
+        let cosine = CosinePdf::new(&rec.normal);
+        let scatter_dir = cosine.generate();
+        let pdf = cosine.value(&scatter_dir);
+        Some(Scatter {
+            ray: Ray::new(rec.p, scatter_dir, rin.time()),
+            attenuation: self.albedo.value(rec.u, rec.v, &rec.p),
+            pdf,
+            specular: false,
+        })
+    }
+
+    // BRDF 是 albedo / π
+    fn eval(&self, _: &Ray, rec: &Record, dir: &Vec3) -> Color {
+        let cosine = Vec3::dot(&rec.normal, &dir.unit());
+        if cosine <= 0.0 {
+            return Color::default();
         }
+        cosine / PI * self.albedo.value(rec.u, rec.v, &rec.p)
+    }
 
-        let scatter = Ray::new(rec.p, scatter_dir);
-        Some((scatter, self.albedo))
+    fn scattering_pdf(&self, _: &Ray, rec: &Record, dir: &Vec3) -> f64 {
+        CosinePdf::new(&rec.normal).value(dir)
+    }
+
+    fn has_diffuse(&self) -> bool {
+        true
     }
 }
 
 pub struct Metal {
-    albedo: Color, // 反射率
-    fuzz: f64,     // 模糊度
+    albedo: Arc<dyn Texture>, // 反射率
+    fuzz: f64,                // 模糊度
 }
 
 impl Metal {
     pub fn new(albedo: Color, fuzz: f64) -> Self {
+        Self::with_texture(Arc::new(SolidColor::new(albedo)), fuzz)
+    }
+
+    pub fn with_texture(albedo: Arc<dyn Texture>, fuzz: f64) -> Self {
         Self { albedo, fuzz }
     }
 }
 
 impl Material for Metal {
-    fn scatter(&self, rin: &Ray, rec: &Record) -> Option<(Ray, Color)> {
+    // 带模糊的反射没有可以计算的 pdf, 也当作镜面处理
+    fn scatter(&self, rin: &Ray, rec: &Record) -> Option<Scatter> {
         let reflected = Vec3::reflect(&rin.direction().unit(), &rec.normal);
-        let scattered = Ray::new(rec.p, reflected + self.fuzz * Vec3::random_in_unit_sphere());
+        let dir = reflected + self.fuzz * Vec3::random_in_unit_sphere();
+        let scattered = Ray::new(rec.p, dir, rin.time());
         if Vec3::dot(scattered.direction(), &rec.normal) > 0.0 {
-            Some((scattered, self.albedo))
+            let attenuation = self.albedo.value(rec.u, rec.v, &rec.p);
+            Some(Scatter::specular(scattered, attenuation))
         } else {
             None
         }
     }
 }
 
+// GGX 微表面导体, albedo 是垂直入射时的反射率 (F0)
+pub struct Conductor {
+    albedo: Arc<dyn Texture>,
+    ggx: Ggx,
+}
+
+impl Conductor {
+    pub fn new(albedo: Color, roughness: f64) -> Self {
+        Self::with_texture(Arc::new(SolidColor::new(albedo)), roughness)
+    }
+
+    pub fn with_texture(albedo: Arc<dyn Texture>, roughness: f64) -> Self {
+        Self {
+            albedo,
+            ggx: Ggx::new(roughness),
+        }
+    }
+}
+
+impl Material for Conductor {
+    // 按可见法线采样微表面, 再以微表面法线做镜面反射
+    fn scatter(&self, rin: &Ray, rec: &Record) -> Option<Scatter> {
+        let f0 = self.albedo.value(rec.u, rec.v, &rec.p);
+        let (uvw, wo) = shading_frame(rin, rec);
+        if self.ggx.is_smooth() {
+            let dir = Vec3::reflect(&rin.direction().unit(), &rec.normal);
+            let attenuation = fresnel_schlick(f0, wo.z());
+            return Some(Scatter::specular(
+                Ray::new(rec.p, dir, rin.time()),
+                attenuation,
+            ));
+        }
+
+        let h = self.ggx.sample(&wo);
+        let wi = microfacet::reflect(&wo, &h);
+        if wi.z() <= 0.0 {
+            return None;
+        }
+
+        let cos = Vec3::dot(&wo, &h);
+        let attenuation = self.ggx.g2(&wo, &wi) / self.ggx.g1(&wo) * fresnel_schlick(f0, cos);
+        Some(Scatter {
+            ray: Ray::new(rec.p, uvw.local(&wi), rin.time()),
+            attenuation,
+            pdf: self.ggx.pdf(&wo, &h) / (4.0 * cos),
+            specular: false,
+        })
+    }
+
+    // D G F / (4 cosθo cosθi) 乘以 cosθi
+    fn eval(&self, rin: &Ray, rec: &Record, dir: &Vec3) -> Color {
+        let (uvw, wo) = shading_frame(rin, rec);
+        let wi = uvw.to_local(&dir.unit());
+        match self.ggx.reflection(&wo, &wi) {
+            Some((h, value, _)) if !self.ggx.is_smooth() => {
+                let f0 = self.albedo.value(rec.u, rec.v, &rec.p);
+                value * fresnel_schlick(f0, Vec3::dot(&wo, &h))
+            }
+            _ => Color::default(),
+        }
+    }
+
+    fn scattering_pdf(&self, rin: &Ray, rec: &Record, dir: &Vec3) -> f64 {
+        let (uvw, wo) = shading_frame(rin, rec);
+        let wi = uvw.to_local(&dir.unit());
+        match self.ggx.reflection(&wo, &wi) {
+            Some((_, _, pdf)) if !self.ggx.is_smooth() => pdf,
+            _ => 0.0,
+        }
+    }
+
+    fn has_diffuse(&self) -> bool {
+        !self.ggx.is_smooth()
+    }
+}
+
+// 折射率随波长的变化, 波长的单位是 μm
+#[derive(Clone, Copy, Debug)]
+pub enum Dispersion {
+    // n = a + b / λ²
+    Cauchy { a: f64, b: f64 },
+    // n² = 1 + Σ b λ² / (λ² - c), 光学玻璃的厂商一般给出这组系数
+    Sellmeier { b: [f64; 3], c: [f64; 3] },
+}
+
+impl Dispersion {
+    // 波长 wavelength (nm) 对应的折射率
+    pub fn ior(&self, wavelength: f64) -> f64 {
+        let l2 = (wavelength / 1000.0).powi(2);
+        match self {
+            Self::Cauchy { a, b } => a + b / l2,
+            Self::Sellmeier { b, c } => {
+                let n2 = 1.0 + (0..3).map(|i| b[i] * l2 / (l2 - c[i])).sum::<f64>();
+                n2.max(1.0).sqrt()
+            }
+        }
+    }
+}
+
 // 电介质
 pub struct Dielectric {
-    ir: f64, // 折射率
+    ir: f64,                        // 折射率, 有色散时是 587.6nm (d 线) 处的折射率
+    dispersion: Option<Dispersion>, // 只在光谱渲染时使用
+    absorption: Color,              // 内部的吸收系数, 用于有颜色的玻璃和液体
 }
 
 impl Dielectric {
     pub fn new(index_of_refraction: f64) -> Self {
+        Self::with_absorption(index_of_refraction, Color::default())
+    }
+
+    pub fn with_absorption(index_of_refraction: f64, absorption: Color) -> Self {
         Self {
             ir: index_of_refraction,
+            dispersion: None,
+            absorption,
+        }
+    }
+
+    pub fn with_dispersion(dispersion: Dispersion, absorption: Color) -> Self {
+        Self {
+            ir: dispersion.ior(587.6),
+            dispersion: Some(dispersion),
+            absorption,
         }
     }
 
@@ -78,12 +281,12 @@ impl Dielectric {
 }
 
 impl Material for Dielectric {
-    fn scatter(&self, rin: &Ray, rec: &Record) -> Option<(Ray, Color)> {
-        let refracttion_ratio = if rec.front_face {
-            1.0 / self.ir
-        } else {
-            self.ir
+    fn scatter(&self, rin: &Ray, rec: &Record) -> Option<Scatter> {
+        let ir = match (&self.dispersion, rin.wavelength()) {
+            (Some(dispersion), Some(wavelength)) => dispersion.ior(wavelength),
+            _ => self.ir,
         };
+        let refracttion_ratio = if rec.front_face { 1.0 / ir } else { ir };
 
         let dir = rin.direction().unit();
         let cos_theta = Vec3::dot(&-dir, &rec.normal).min(1.0);
@@ -93,7 +296,7 @@ impl Material for Dielectric {
         let cannot_refract = refracttion_ratio * sin_theta > 1.0;
 
         // 不是很懂...
-        let rn = rand::thread_rng().gen_range(0.0..1.0);
+        let rn = random::random_double();
         let rn = self.reflectance(cos_theta, refracttion_ratio) > rn;
 
         let dir = if cannot_refract || rn {
@@ -102,8 +305,834 @@ impl Material for Dielectric {
             Vec3::refract(&dir, &rec.normal, refracttion_ratio)
         };
 
-        let scattered = Ray::new(rec.p, dir);
+        let scattered = Ray::new(rec.p, dir, rin.time());
         let attenuation = Color::new(1.0, 1.0, 1.0);
-        Some((scattered, attenuation))
+        Some(Scatter::specular(scattered, attenuation))
+    }
+
+    fn absorption(&self) -> Color {
+        self.absorption
+    }
+
+    fn wavelength_dependent(&self) -> bool {
+        self.dispersion.is_some()
+    }
+}
+
+// 表面覆盖一层薄膜的电介质或金属, 反射率由薄膜的干涉决定, 用于肥皂泡, 油膜和镀膜镜头
+pub struct ThinFilm {
+    film: Film,
+    base: FilmBase,
+    fuzz: f64, // 金属基底的模糊度
+}
+
+impl ThinFilm {
+    // 薄膜下是折射率为 ir 的电介质, 肥皂泡的 ir 是 1
+    pub fn dielectric(ir: f64, thickness: f64, film_ior: f64) -> Self {
+        Self {
+            film: Film::new(thickness, film_ior),
+            base: FilmBase::Dielectric(ir),
+            fuzz: 0.0,
+        }
+    }
+
+    pub fn metal(albedo: Color, fuzz: f64, thickness: f64, film_ior: f64) -> Self {
+        Self {
+            film: Film::new(thickness, film_ior),
+            base: FilmBase::Conductor(albedo),
+            fuzz,
+        }
+    }
+}
+
+impl Material for ThinFilm {
+    fn scatter(&self, rin: &Ray, rec: &Record) -> Option<Scatter> {
+        let dir = rin.direction().unit();
+        let cos_i = Vec3::dot(&-dir, &rec.normal).min(1.0);
+
+        let FilmBase::Dielectric(ir) = self.base else {
+            let reflectance = self
+                .film
+                .reflectance(cos_i, 1.0, &self.base, rin.wavelength());
+            let dir = Vec3::reflect(&dir, &rec.normal) + self.fuzz * Vec3::random_in_unit_sphere();
+            if Vec3::dot(&dir, &rec.normal) <= 0.0 {
+                return None;
+            }
+            return Some(Scatter::specular(
+                Ray::new(rec.p, dir, rin.time()),
+                reflectance,
+            ));
+        };
+
+        // 薄膜在物体外侧, 从内部射出时先经过基底
+        let (n1, n3) = match rec.front_face {
+            true => (1.0, ir),
+            false => (ir, 1.0),
+        };
+        let base = FilmBase::Dielectric(n3);
+        let reflectance = self.film.reflectance(cos_i, n1, &base, rin.wavelength());
+
+        // 按平均反射率选择反射或折射, 再用各通道的比例修正颜色
+        let p = (reflectance.x() + reflectance.y() + reflectance.z()) / 3.0;
+        let (dir, attenuation) = if random::random_double() < p {
+            (Vec3::reflect(&dir, &rec.normal), reflectance / p)
+        } else {
+            let transmittance = Color::new(1.0, 1.0, 1.0) - reflectance;
+            let dir = Vec3::refract(&dir, &rec.normal, n1 / n3);
+            (dir, transmittance / (1.0 - p))
+        };
+        Some(Scatter::specular(
+            Ray::new(rec.p, dir, rin.time()),
+            attenuation,
+        ))
+    }
+
+    fn wavelength_dependent(&self) -> bool {
+        true
+    }
+}
+
+// GGX 微表面电介质 (毛玻璃), 按菲涅尔反射率在反射和折射之间随机选择
+pub struct RoughDielectric {
+    ir: f64,
+    ggx: Ggx,
+}
+
+impl RoughDielectric {
+    pub fn new(index_of_refraction: f64, roughness: f64) -> Self {
+        Self {
+            ir: index_of_refraction,
+            ggx: Ggx::new(roughness),
+        }
+    }
+
+    // 透射侧与入射侧折射率之比
+    fn eta(&self, rec: &Record) -> f64 {
+        match rec.front_face {
+            true => self.ir,
+            false => 1.0 / self.ir,
+        }
+    }
+
+    // wo 和 wi 对应的微表面法线, 朝向 wo 一侧
+    fn half_vector(wo: &Vec3, wi: &Vec3, eta: f64) -> Vec3 {
+        let h = match wi.z() > 0.0 {
+            true => *wo + *wi,
+            false => -(*wo + eta * *wi),
+        };
+        let h = h.unit();
+        match h.z() < 0.0 {
+            true => -h,
+            false => h,
+        }
+    }
+
+    // 返回 wi 方向的 BSDF 乘以余弦项以及采样到 wi 的概率密度
+    // 折射部分省略了辐射亮度的 1 / η² 缩放, 与光滑的 Dielectric 保持一致
+    fn evaluate(&self, rin: &Ray, rec: &Record, dir: &Vec3) -> (f64, f64) {
+        let (uvw, wo) = shading_frame(rin, rec);
+        let wi = uvw.to_local(&dir.unit());
+        let eta = self.eta(rec);
+        if self.ggx.is_smooth() || wi.z() == 0.0 {
+            return (0.0, 0.0);
+        }
+
+        let h = Self::half_vector(&wo, &wi, eta);
+        let (cos_o, cos_i) = (Vec3::dot(&wo, &h), Vec3::dot(&wi, &h));
+        if cos_o <= 0.0 {
+            return (0.0, 0.0);
+        }
+
+        let f = fresnel_dielectric(cos_o, eta);
+        let d = self.ggx.d(&h);
+        let g = self.ggx.g2(&wo, &wi);
+        if wi.z() > 0.0 {
+            let value = f * d * g / (4.0 * wo.z());
+            let pdf = f * self.ggx.pdf(&wo, &h) / (4.0 * cos_o);
+            return (value, pdf);
+        }
+
+        // 折射方向必须在微表面的另一侧
+        if cos_i >= 0.0 {
+            return (0.0, 0.0);
+        }
+        let denom = cos_o + eta * cos_i;
+        let jacobian = eta * eta * cos_i.abs() / (denom * denom);
+        let value = (1.0 - f) * d * g * cos_o / wo.z() * jacobian;
+        let pdf = (1.0 - f) * self.ggx.pdf(&wo, &h) * jacobian;
+        (value, pdf)
+    }
+}
+
+impl Material for RoughDielectric {
+    fn scatter(&self, rin: &Ray, rec: &Record) -> Option<Scatter> {
+        if self.ggx.is_smooth() {
+            return Dielectric::new(self.ir).scatter(rin, rec);
+        }
+
+        let (uvw, wo) = shading_frame(rin, rec);
+        let eta = self.eta(rec);
+        let h = self.ggx.sample(&wo);
+        let cos_o = Vec3::dot(&wo, &h);
+        let f = fresnel_dielectric(cos_o, eta);
+
+        let wi = match random::random_double() < f {
+            true => microfacet::reflect(&wo, &h),
+            false => microfacet::refract(&wo, &h, eta)?,
+        };
+        let reflected = wi.z() > 0.0;
+        if reflected != (Vec3::dot(&wi, &h) > 0.0) {
+            return None;
+        }
+
+        let dir = uvw.local(&wi);
+        let (_, pdf) = self.evaluate(rin, rec, &dir);
+        if pdf <= 0.0 {
+            return None;
+        }
+
+        // 反射和折射的概率正好抵消了菲涅尔项, 只剩下遮蔽项
+        let g = self.ggx.g2(&wo, &wi) / self.ggx.g1(&wo);
+        Some(Scatter {
+            ray: Ray::new(rec.p, dir, rin.time()),
+            attenuation: Color::new(g, g, g),
+            pdf,
+            specular: false,
+        })
+    }
+
+    fn eval(&self, rin: &Ray, rec: &Record, dir: &Vec3) -> Color {
+        let (value, _) = self.evaluate(rin, rec, dir);
+        Color::new(value, value, value)
+    }
+
+    fn scattering_pdf(&self, rin: &Ray, rec: &Record, dir: &Vec3) -> f64 {
+        self.evaluate(rin, rec, dir).1
+    }
+
+    fn has_diffuse(&self) -> bool {
+        !self.ggx.is_smooth()
+    }
+}
+
+// Disney 风格的 principled 材质, 用一组参数组合漫反射, 光泽 (sheen), 高光, 清漆和透射五个部分
+// 各参数都在 [0, 1] 之间, 与常见 PBR 工具导出的 metallic/roughness 参数一致
+pub struct Principled {
+    pub base_color: Arc<dyn Texture>,
+    pub metallic: f64,
+    pub roughness: f64, // 按不小于 0.05 处理, 完全光滑的表面请用 Metal 或 Dielectric
+    pub specular: f64,  // 非金属的高光强度, 0.5 对应 F0 = 0.04
+    pub specular_tint: f64,
+    pub sheen: f64, // 掠射角的绒毛光泽, 用于布料
+    pub sheen_tint: f64,
+    pub clearcoat: f64, // 表面额外的一层清漆
+    pub clearcoat_gloss: f64,
+    pub transmission: f64, // 非金属部分透射的比例
+    pub ior: f64,
+}
+
+impl Principled {
+    pub fn new(base_color: Color) -> Self {
+        Self::with_texture(Arc::new(SolidColor::new(base_color)))
+    }
+
+    pub fn with_texture(base_color: Arc<dyn Texture>) -> Self {
+        Self {
+            base_color,
+            metallic: 0.0,
+            roughness: 0.5,
+            specular: 0.5,
+            specular_tint: 0.0,
+            sheen: 0.0,
+            sheen_tint: 0.5,
+            clearcoat: 0.0,
+            clearcoat_gloss: 1.0,
+            transmission: 0.0,
+            ior: 1.5,
+        }
+    }
+
+    fn ggx(&self) -> Ggx {
+        Ggx::new(self.roughness.max(0.05))
+    }
+
+    fn clearcoat_ggx(&self) -> Ggx {
+        let gloss = self.clearcoat_gloss.clamp(0.0, 1.0);
+        Ggx::new((1.0 - gloss) * 0.3 + gloss * 0.05)
+    }
+
+    fn glass(&self) -> RoughDielectric {
+        RoughDielectric::new(self.ior, self.roughness.max(0.05))
+    }
+
+    // 各个部分的采样概率: 漫反射 (含 sheen), 高光, 透射, 清漆
+    fn lobe_weights(&self, rec: &Record) -> [f64; 4] {
+        // 从物体内部射出时只有透射的界面
+        if !rec.front_face && self.transmission > 0.0 {
+            return [0.0, 0.0, 1.0, 0.0];
+        }
+
+        let metallic = self.metallic.clamp(0.0, 1.0);
+        let transmission = self.transmission.clamp(0.0, 1.0);
+        let weights = [
+            (1.0 - metallic) * (1.0 - transmission),
+            1.0,
+            (1.0 - metallic) * transmission,
+            0.25 * self.clearcoat.clamp(0.0, 1.0),
+        ];
+        let sum: f64 = weights.iter().sum();
+        weights.map(|w| w / sum)
+    }
+
+    // wi 方向的 BSDF 乘以余弦项, 以及按 lobe_weights 混合采样到 wi 的概率密度
+    fn evaluate(&self, rin: &Ray, rec: &Record, dir: &Vec3) -> (Color, f64) {
+        let weights = self.lobe_weights(rec);
+        let base = self.base_color.value(rec.u, rec.v, &rec.p);
+        let metallic = self.metallic.clamp(0.0, 1.0);
+        let transmission = self.transmission.clamp(0.0, 1.0);
+
+        let (uvw, wo) = shading_frame(rin, rec);
+        let wi = uvw.to_local(&dir.unit());
+        let mut value = Color::default();
+        let mut pdf = 0.0;
+
+        // 透射部分, 进入物体时折射光带上基础色
+        if weights[2] > 0.0 {
+            let (v, p) = self.glass().evaluate(rin, rec, dir);
+            let tint = match rec.front_face && wi.z() < 0.0 {
+                true => base,
+                false => Color::new(1.0, 1.0, 1.0),
+            };
+            let w = match rec.front_face {
+                true => (1.0 - metallic) * transmission,
+                false => 1.0,
+            };
+            value += w * v * tint;
+            pdf += weights[2] * p;
+        }
+        if !rec.front_face && self.transmission > 0.0 {
+            return (value, pdf);
+        }
+        if wo.z() <= 0.0 || wi.z() <= 0.0 {
+            return (value, pdf);
+        }
+
+        // 漫反射: Burley 漫反射加上 sheen
+        let h = (wo + wi).unit();
+        let cos_d = Vec3::dot(&wi, &h);
+        let luminance = 0.2126 * base.x() + 0.7152 * base.y() + 0.0722 * base.z();
+        let tint = match luminance > 0.0 {
+            true => base / luminance,
+            false => Color::new(1.0, 1.0, 1.0),
+        };
+        let lerp = |a: Color, b: Color, t: f64| (1.0 - t) * a + t * b;
+        let white = Color::new(1.0, 1.0, 1.0);
+
+        let fd90 = 0.5 + 2.0 * self.roughness * cos_d * cos_d;
+        let fl = (1.0 - wi.z()).powi(5);
+        let fv = (1.0 - wo.z()).powi(5);
+        let diffuse = (1.0 + (fd90 - 1.0) * fl) * (1.0 + (fd90 - 1.0) * fv) / PI * base;
+        let sheen = self.sheen * (1.0 - cos_d).powi(5) * lerp(white, tint, self.sheen_tint);
+        let w = (1.0 - metallic) * (1.0 - transmission);
+        value += w * wi.z() * (diffuse + sheen);
+        pdf += weights[0] * wi.z() / PI;
+
+        // 高光: 非金属的 F0 由 specular 决定, 金属的 F0 就是基础色
+        if let Some((h, v, p)) = self.ggx().reflection(&wo, &wi) {
+            let dielectric = 0.08 * self.specular * lerp(white, tint, self.specular_tint);
+            let f0 = lerp(dielectric, base, metallic);
+            value += v * fresnel_schlick(f0, Vec3::dot(&wo, &h));
+            pdf += weights[1] * p;
+        }
+
+        // 清漆: F0 = 0.04 的无色高光
+        if weights[3] > 0.0 {
+            if let Some((h, v, p)) = self.clearcoat_ggx().reflection(&wo, &wi) {
+                let f = fresnel_schlick(Color::new(0.04, 0.04, 0.04), Vec3::dot(&wo, &h));
+                value += 0.25 * self.clearcoat * v * f;
+                pdf += weights[3] * p;
+            }
+        }
+
+        (value, pdf)
     }
 }
+
+impl Material for Principled {
+    // 按权重随机选择一个部分采样方向, 再用所有部分的 BSDF 和混合概率密度计算衰减
+    fn scatter(&self, rin: &Ray, rec: &Record) -> Option<Scatter> {
+        let weights = self.lobe_weights(rec);
+        let (uvw, wo) = shading_frame(rin, rec);
+
+        let mut xi = random::random_double();
+        let lobe = weights
+            .iter()
+            .position(|&w| {
+                xi -= w;
+                xi < 0.0
+            })
+            .unwrap_or(1);
+
+        let dir = match lobe {
+            0 => CosinePdf::new(&rec.normal).generate(),
+            1 => uvw.local(&microfacet::reflect(&wo, &self.ggx().sample(&wo))),
+            2 => *self.glass().scatter(rin, rec)?.ray.direction(),
+            _ => {
+                let h = self.clearcoat_ggx().sample(&wo);
+                uvw.local(&microfacet::reflect(&wo, &h))
+            }
+        };
+
+        let (value, pdf) = self.evaluate(rin, rec, &dir);
+        if pdf <= 0.0 || value.near_zero() {
+            return None;
+        }
+
+        Some(Scatter {
+            ray: Ray::new(rec.p, dir, rin.time()),
+            attenuation: value / pdf,
+            pdf,
+            specular: false,
+        })
+    }
+
+    fn eval(&self, rin: &Ray, rec: &Record, dir: &Vec3) -> Color {
+        self.evaluate(rin, rec, dir).0
+    }
+
+    fn scattering_pdf(&self, rin: &Ray, rec: &Record, dir: &Vec3) -> f64 {
+        self.evaluate(rin, rec, dir).1
+    }
+
+    fn has_diffuse(&self) -> bool {
+        true
+    }
+}
+
+// 漫反射光源, 只发光不散射
+pub struct DiffuseLight {
+    emit: Color, // 辐射亮度
+}
+
+impl DiffuseLight {
+    pub fn new(emit: Color) -> Self {
+        Self { emit }
+    }
+}
+
+impl Material for DiffuseLight {
+    fn scatter(&self, _: &Ray, _: &Record) -> Option<Scatter> {
+        None
+    }
+
+    fn emitted(&self, _: &Ray, _: &Record) -> Color {
+        self.emit
+    }
+}
+
+// 各向同性的相函数, 用于参与介质, 向所有方向均匀散射
+pub struct Isotropic {
+    albedo: Arc<dyn Texture>,
+}
+
+impl Isotropic {
+    pub fn new(albedo: Color) -> Self {
+        Self::with_texture(Arc::new(SolidColor::new(albedo)))
+    }
+
+    pub fn with_texture(albedo: Arc<dyn Texture>) -> Self {
+        Self { albedo }
+    }
+}
+
+impl Material for Isotropic {
+    fn scatter(&self, rin: &Ray, rec: &Record) -> Option<Scatter> {
+        let dir = SpherePdf.generate();
+        Some(Scatter {
+            ray: Ray::new(rec.p, dir, rin.time()),
+            attenuation: self.albedo.value(rec.u, rec.v, &rec.p),
+            pdf: SpherePdf.value(&dir),
+            specular: false,
+        })
+    }
+
+    // 相函数是 1 / 4π, 介质中没有余弦项
+    fn eval(&self, _: &Ray, rec: &Record, _: &Vec3) -> Color {
+        self.albedo.value(rec.u, rec.v, &rec.p) / (4.0 * PI)
+    }
+
+    fn scattering_pdf(&self, _: &Ray, _: &Record, dir: &Vec3) -> f64 {
+        SpherePdf.value(dir)
+    }
+
+    fn has_diffuse(&self) -> bool {
+        true
+    }
+}
+
+// Henyey-Greenstein 相函数, g 在 (-1, 1) 之间控制各向异性:
+// g > 0 时偏向前向散射, g < 0 时偏向后向散射, g = 0 时等同于 Isotropic
+pub struct HenyeyGreenstein {
+    albedo: Arc<dyn Texture>,
+    g: f64,
+}
+
+impl HenyeyGreenstein {
+    pub fn new(albedo: Color, g: f64) -> Self {
+        Self::with_texture(Arc::new(SolidColor::new(albedo)), g)
+    }
+
+    pub fn with_texture(albedo: Arc<dyn Texture>, g: f64) -> Self {
+        Self {
+            albedo,
+            g: g.clamp(-0.999, 0.999),
+        }
+    }
+
+    // 散射方向与入射方向夹角的余弦为 cos_theta 时相函数的值
+    fn phase(&self, cos_theta: f64) -> f64 {
+        let g = self.g;
+        let denom = 1.0 + g * g - 2.0 * g * cos_theta;
+        (1.0 - g * g) / (4.0 * PI * denom * denom.sqrt())
+    }
+
+    // 按相函数采样散射方向与入射方向夹角的余弦
+    fn sample_cos_theta(&self) -> f64 {
+        let xi = random::random_double();
+        let g = self.g;
+        if g.abs() < 1e-3 {
+            return 1.0 - 2.0 * xi;
+        }
+
+        let s = (1.0 - g * g) / (1.0 - g + 2.0 * g * xi);
+        ((1.0 + g * g - s * s) / (2.0 * g)).clamp(-1.0, 1.0)
+    }
+}
+
+impl Material for HenyeyGreenstein {
+    // 按相函数采样, 相函数本身就是概率密度, 所以衰减只有 albedo
+    fn scatter(&self, rin: &Ray, rec: &Record) -> Option<Scatter> {
+        let cos_theta = self.sample_cos_theta();
+        let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
+        let phi = 2.0 * PI * random::random_double();
+        let local = Vec3::new(sin_theta * phi.cos(), sin_theta * phi.sin(), cos_theta);
+        let dir = Onb::new(rin.direction()).local(&local);
+
+        Some(Scatter {
+            ray: Ray::new(rec.p, dir, rin.time()),
+            attenuation: self.albedo.value(rec.u, rec.v, &rec.p),
+            pdf: self.phase(cos_theta),
+            specular: false,
+        })
+    }
+
+    fn eval(&self, rin: &Ray, rec: &Record, dir: &Vec3) -> Color {
+        let pdf = self.scattering_pdf(rin, rec, dir);
+        pdf * self.albedo.value(rec.u, rec.v, &rec.p)
+    }
+
+    fn scattering_pdf(&self, rin: &Ray, _: &Record, dir: &Vec3) -> f64 {
+        let cos_theta = Vec3::dot(&rin.direction().unit(), &dir.unit());
+        self.phase(cos_theta)
+    }
+
+    fn has_diffuse(&self) -> bool {
+        true
+    }
+}
+
+// 按权重在两个材质之间随机选择, weight 是选择 b 的概率, 纹理取三个通道的平均值
+pub struct MixMaterial {
+    a: Arc<dyn Material>,
+    b: Arc<dyn Material>,
+    weight: Arc<dyn Texture>,
+}
+
+impl MixMaterial {
+    pub fn new(a: Arc<dyn Material>, b: Arc<dyn Material>, weight: f64) -> Self {
+        let weight = Color::new(weight, weight, weight);
+        Self::with_texture(a, b, Arc::new(SolidColor::new(weight)))
+    }
+
+    pub fn with_texture(
+        a: Arc<dyn Material>,
+        b: Arc<dyn Material>,
+        weight: Arc<dyn Texture>,
+    ) -> Self {
+        Self { a, b, weight }
+    }
+
+    fn weight(&self, rec: &Record) -> f64 {
+        let w = self.weight.value(rec.u, rec.v, &rec.p);
+        ((w.x() + w.y() + w.z()) / 3.0).clamp(0.0, 1.0)
+    }
+}
+
+impl Material for MixMaterial {
+    // 选中的材质按自身的方式采样, 非镜面方向再按两个材质的混合计算衰减和概率密度,
+    // 这样 scatter 与 eval, scattering_pdf 一致, 可以和光源采样结合
+    fn scatter(&self, rin: &Ray, rec: &Record) -> Option<Scatter> {
+        let w = self.weight(rec);
+        let chosen = match random::random_double() < w {
+            true => &self.b,
+            false => &self.a,
+        };
+
+        let scatter = chosen.scatter(rin, rec)?;
+        if scatter.specular {
+            return Some(scatter);
+        }
+
+        let dir = scatter.ray.direction();
+        let pdf = self.scattering_pdf(rin, rec, dir);
+        if pdf <= 0.0 {
+            return None;
+        }
+        Some(Scatter {
+            attenuation: self.eval(rin, rec, dir) / pdf,
+            pdf,
+            ..scatter
+        })
+    }
+
+    fn emitted(&self, rin: &Ray, rec: &Record) -> Color {
+        let w = self.weight(rec);
+        (1.0 - w) * self.a.emitted(rin, rec) + w * self.b.emitted(rin, rec)
+    }
+
+    fn eval(&self, rin: &Ray, rec: &Record, dir: &Vec3) -> Color {
+        let w = self.weight(rec);
+        (1.0 - w) * self.a.eval(rin, rec, dir) + w * self.b.eval(rin, rec, dir)
+    }
+
+    fn scattering_pdf(&self, rin: &Ray, rec: &Record, dir: &Vec3) -> f64 {
+        let w = self.weight(rec);
+        (1.0 - w) * self.a.scattering_pdf(rin, rec, dir) + w * self.b.scattering_pdf(rin, rec, dir)
+    }
+
+    fn wavelength_dependent(&self) -> bool {
+        self.a.wavelength_dependent() || self.b.wavelength_dependent()
+    }
+
+    fn has_diffuse(&self) -> bool {
+        self.a.has_diffuse() || self.b.has_diffuse()
+    }
+}
+
+// 在任意材质外面涂一层光滑透明的电介质 (车漆, 清漆木材)
+// 按菲涅尔反射率在涂层表面镜面反射, 否则由基底材质散射, 进出涂层各损失一次菲涅尔透射,
+// 忽略涂层内的折射和多次反射
+pub struct Coated {
+    base: Arc<dyn Material>,
+    ir: f64, // 涂层的折射率
+}
+
+impl Coated {
+    pub fn new(base: Arc<dyn Material>, ir: f64) -> Self {
+        Self { base, ir }
+    }
+
+    // 光线沿 dir 方向进出涂层时的菲涅尔反射率
+    fn fresnel(&self, rec: &Record, dir: &Vec3) -> f64 {
+        let cos = Vec3::dot(&dir.unit(), &rec.normal).abs();
+        fresnel_dielectric(cos, self.ir)
+    }
+}
+
+impl Material for Coated {
+    fn scatter(&self, rin: &Ray, rec: &Record) -> Option<Scatter> {
+        // 从物体内部射出时不经过涂层
+        if !rec.front_face {
+            return self.base.scatter(rin, rec);
+        }
+
+        let fo = self.fresnel(rec, rin.direction());
+        if random::random_double() < fo {
+            let dir = Vec3::reflect(&rin.direction().unit(), &rec.normal);
+            let ray = Ray::new(rec.p, dir, rin.time());
+            return Some(Scatter::specular(ray, Color::new(1.0, 1.0, 1.0)));
+        }
+
+        let scatter = self.base.scatter(rin, rec)?;
+        let fi = self.fresnel(rec, scatter.ray.direction());
+        Some(Scatter {
+            attenuation: (1.0 - fi) * scatter.attenuation,
+            pdf: (1.0 - fo) * scatter.pdf,
+            ..scatter
+        })
+    }
+
+    fn emitted(&self, rin: &Ray, rec: &Record) -> Color {
+        let emitted = self.base.emitted(rin, rec);
+        match rec.front_face {
+            true => (1.0 - self.fresnel(rec, rin.direction())) * emitted,
+            false => emitted,
+        }
+    }
+
+    fn eval(&self, rin: &Ray, rec: &Record, dir: &Vec3) -> Color {
+        let f = self.base.eval(rin, rec, dir);
+        if !rec.front_face {
+            return f;
+        }
+        let fo = self.fresnel(rec, rin.direction());
+        let fi = self.fresnel(rec, dir);
+        (1.0 - fo) * (1.0 - fi) * f
+    }
+
+    fn scattering_pdf(&self, rin: &Ray, rec: &Record, dir: &Vec3) -> f64 {
+        let pdf = self.base.scattering_pdf(rin, rec, dir);
+        match rec.front_face {
+            true => (1.0 - self.fresnel(rec, rin.direction())) * pdf,
+            false => pdf,
+        }
+    }
+
+    fn absorption(&self) -> Color {
+        self.base.absorption()
+    }
+
+    fn wavelength_dependent(&self) -> bool {
+        self.base.wavelength_dependent()
+    }
+
+    fn has_diffuse(&self) -> bool {
+        self.base.has_diffuse()
+    }
+}
+
+// 着色法线的来源
+enum NormalSource {
+    // 切线空间的法线贴图, 颜色 [0, 1] 映射到 [-1, 1], 蓝色通道沿着表面法线
+    NormalMap {
+        map: Arc<dyn Texture>,
+        strength: f64,
+    },
+    // 高度场, 按高度沿切向量的变化倾斜法线
+    Bump {
+        height: Arc<dyn Texture>,
+        scale: f64,
+    },
+}
+
+// 不改变几何形状, 只扰动撞击点的法线再交给基底材质, 让表面有细小的凹凸
+pub struct ShadingNormal {
+    base: Arc<dyn Material>,
+    source: NormalSource,
+}
+
+impl ShadingNormal {
+    // strength 缩放贴图法线的切向分量, 为 0 时没有效果
+    pub fn normal_map(base: Arc<dyn Material>, map: Arc<dyn Texture>, strength: f64) -> Self {
+        Self {
+            base,
+            source: NormalSource::NormalMap { map, strength },
+        }
+    }
+
+    // 高度取纹理三个通道的平均值乘以 scale, 单位与场景相同
+    pub fn bump(base: Arc<dyn Material>, height: Arc<dyn Texture>, scale: f64) -> Self {
+        Self {
+            base,
+            source: NormalSource::Bump { height, scale },
+        }
+    }
+
+    // 用扰动后的法线替换 rec 中的法线
+    fn shade<'a>(&self, rin: &Ray, rec: &Record<'a>) -> Record<'a> {
+        // 切向量是相对向外的法线定义的, 在外侧计算完再翻转回光线所在的一侧
+        let n = match rec.front_face {
+            true => rec.normal,
+            false => -rec.normal,
+        };
+        // 没有参数化的表面取法线所在平面内任意两个正交的单位向量作为切向量
+        let (dpdu, dpdv) = match rec.dpdu.near_zero() {
+            true => {
+                let uvw = Onb::new(&n);
+                (*uvw.u(), *uvw.v())
+            }
+            false => (rec.dpdu, rec.dpdv),
+        };
+        let tangent = dpdu - Vec3::dot(&dpdu, &n) * n;
+        if tangent.near_zero() {
+            return *rec;
+        }
+        let tangent = tangent.unit();
+        let bitangent = match Vec3::dot(&Vec3::cross(&n, &tangent), &dpdv) < 0.0 {
+            true => -Vec3::cross(&n, &tangent),
+            false => Vec3::cross(&n, &tangent),
+        };
+
+        let normal = match &self.source {
+            NormalSource::NormalMap { map, strength } => {
+                let c = map.value(rec.u, rec.v, &rec.p);
+                let (x, y, z) = (2.0 * c.x() - 1.0, 2.0 * c.y() - 1.0, 2.0 * c.z() - 1.0);
+                *strength * (x * tangent + y * bitangent) + z.max(0.0) * n
+            }
+            NormalSource::Bump { height, scale } => {
+                // 有限差分求高度对 u, v 的导数, 再除以切向量的长度换算成沿表面的梯度
+                let h = |u: f64, v: f64, p: &Point3| {
+                    let c = height.value(u, v, p);
+                    scale * (c.x() + c.y() + c.z()) / 3.0
+                };
+                let delta = 0.0005;
+                let h0 = h(rec.u, rec.v, &rec.p);
+                let hu = h(rec.u + delta, rec.v, &(rec.p + delta * dpdu));
+                let hv = h(rec.u, rec.v + delta, &(rec.p + delta * dpdv));
+                let gu = (hu - h0) / (delta * dpdu.length());
+                let gv = (hv - h0) / (delta * dpdv.length().max(1e-12));
+                n - gu * tangent - gv * bitangent
+            }
+        };
+        if normal.near_zero() {
+            return *rec;
+        }
+
+        let normal = match rec.front_face {
+            true => normal.unit(),
+            false => -normal.unit(),
+        };
+        // 扰动后的法线转到了背对观察者的一侧时, 按它着色会把光反射进表面内部, 改用几何法线
+        if Vec3::dot(rin.direction(), &normal) >= 0.0 {
+            return *rec;
+        }
+        Record { normal, ..*rec }
+    }
+}
+
+impl Material for ShadingNormal {
+    fn scatter(&self, rin: &Ray, rec: &Record) -> Option<Scatter> {
+        self.base.scatter(rin, &self.shade(rin, rec))
+    }
+
+    fn emitted(&self, rin: &Ray, rec: &Record) -> Color {
+        self.base.emitted(rin, rec)
+    }
+
+    fn eval(&self, rin: &Ray, rec: &Record, dir: &Vec3) -> Color {
+        self.base.eval(rin, &self.shade(rin, rec), dir)
+    }
+
+    fn scattering_pdf(&self, rin: &Ray, rec: &Record, dir: &Vec3) -> f64 {
+        self.base.scattering_pdf(rin, &self.shade(rin, rec), dir)
+    }
+
+    fn absorption(&self) -> Color {
+        self.base.absorption()
+    }
+
+    fn wavelength_dependent(&self) -> bool {
+        self.base.wavelength_dependent()
+    }
+
+    fn has_diffuse(&self) -> bool {
+        self.base.has_diffuse()
+    }
+}
+
+// 以撞击点法线为 z 轴的局部坐标系, 以及局部坐标系中指向光线来处的方向
+fn shading_frame(rin: &Ray, rec: &Record) -> (Onb, Vec3) {
+    let uvw = Onb::new(&rec.normal);
+    let wo = uvw.to_local(&-rin.direction().unit());
+    (uvw, wo)
+}
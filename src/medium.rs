@@ -0,0 +1,299 @@
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+
+use anyhow::{bail, Context, Result};
+
+use crate::{
+    aabb::Aabb,
+    hit::{Hittable, Record},
+    material::{Isotropic, Material},
+    perlin::Perlin,
+    random,
+    ray::Ray,
+    vec3::{Color, Point3, Vec3},
+};
+
+// 密度均匀的参与介质 (烟, 雾), 由一个封闭的边界物体确定范围
+// 光线在介质中走过距离 d 后发生散射的概率是 density * d
+pub struct ConstantMedium<T> {
+    boundary: T,
+    neg_inv_density: f64,
+    phase: Arc<dyn Material>, // 相函数, 决定散射后的方向
+}
+
+impl<T: Hittable> ConstantMedium<T> {
+    pub fn new(boundary: T, density: f64, albedo: Color) -> Self {
+        Self::with_phase(boundary, density, Arc::new(Isotropic::new(albedo)))
+    }
+
+    pub fn with_phase(boundary: T, density: f64, phase: Arc<dyn Material>) -> Self {
+        Self {
+            boundary,
+            neg_inv_density: -1.0 / density,
+            phase,
+        }
+    }
+}
+
+impl<T: Hittable> Hittable for ConstantMedium<T> {
+    fn hit(&self, r: &Ray, min: f64, max: f64) -> Option<Record<'_>> {
+        let (t0, t1) = interval(&self.boundary, r, min, max)?;
+
+        // 按指数分布采样散射距离, 超出边界说明光线直接穿过了介质
+        let length = r.direction().length();
+        let distance = (t1 - t0) * length;
+        let hit_distance = self.neg_inv_density * random::random_double().ln();
+        if hit_distance > distance {
+            return None;
+        }
+
+        // 介质内部没有表面, 法线和正反面都是任意取的
+        let t = t0 + hit_distance / length;
+        let normal = Vec3::new(1.0, 0.0, 0.0);
+        let mut rec = Record::new(r, t, normal, (0.0, 0.0), self.phase.as_ref());
+        rec.front_face = true;
+        Some(rec)
+    }
+
+    // 介质没有表面, 不会挡住阴影光线
+    fn hit_surface(&self, _r: &Ray, _min: f64, _max: f64) -> Option<Record<'_>> {
+        None
+    }
+
+    // 密度均匀时透射率有解析解 exp(-density * d)
+    fn transmittance(&self, r: &Ray, min: f64, max: f64) -> f64 {
+        match interval(&self.boundary, r, min, max) {
+            Some((t0, t1)) => ((t1 - t0) * r.direction().length() / self.neg_inv_density).exp(),
+            None => 1.0,
+        }
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        self.boundary.bounding_box()
+    }
+}
+
+// 光线在边界内部的区间 [t0, t1], 要求边界是凸的
+fn interval<T: Hittable>(boundary: &T, r: &Ray, min: f64, max: f64) -> Option<(f64, f64)> {
+    let enter = boundary.hit(r, f64::NEG_INFINITY, f64::INFINITY)?;
+    let exit = boundary.hit(r, enter.t + 0.0001, f64::INFINITY)?;
+
+    let t0 = enter.t.max(min).max(0.0);
+    let t1 = exit.t.min(max);
+    (t0 < t1).then_some((t0, t1))
+}
+
+// 空间中变化的密度场
+pub trait Density: Send + Sync {
+    fn density(&self, p: &Point3) -> f64;
+
+    // 密度的上界, delta tracking 用它作为采样的主密度
+    fn max_density(&self) -> f64;
+}
+
+// 用 Perlin 湍流生成的密度, 取值在 [0, density] 之间
+pub struct NoiseDensity {
+    noise: Perlin,
+    scale: f64,
+    density: f64,
+}
+
+impl NoiseDensity {
+    pub fn new(seed: u64, scale: f64, density: f64) -> Self {
+        Self {
+            noise: Perlin::new(seed),
+            scale,
+            density,
+        }
+    }
+}
+
+impl Density for NoiseDensity {
+    fn density(&self, p: &Point3) -> f64 {
+        let t = self.noise.turb(&(self.scale * *p), 7);
+        self.density * t.clamp(0.0, 1.0)
+    }
+
+    fn max_density(&self) -> f64 {
+        self.density
+    }
+}
+
+// 体素网格密度, 网格铺满 bounds, 体素之间做三线性插值
+pub struct GridDensity {
+    bounds: Aabb,
+    size: [usize; 3],
+    data: Vec<f32>,
+    scale: f64,
+    max: f64,
+}
+
+impl GridDensity {
+    pub fn new(bounds: Aabb, size: [usize; 3], data: Vec<f32>, scale: f64) -> Self {
+        assert_eq!(data.len(), size.iter().product::<usize>());
+        let max = data.iter().fold(0.0f32, |a, &b| a.max(b)) as f64 * scale;
+        Self {
+            bounds,
+            size,
+            data,
+            scale,
+            max,
+        }
+    }
+
+    // 读取原始体素文件: nx * ny * nz 个小端序 f32, x 变化最快, z 变化最慢
+    pub fn open<P: AsRef<Path>>(
+        path: P,
+        size: [usize; 3],
+        bounds: Aabb,
+        scale: f64,
+    ) -> Result<Self> {
+        let path = path.as_ref();
+        let bytes = fs::read(path).with_context(|| format!("open {}", path.display()))?;
+
+        let count = size.iter().product::<usize>();
+        if bytes.len() != count * 4 {
+            bail!(
+                "{}: expect {} bytes for {}x{}x{} voxels, found {}",
+                path.display(),
+                count * 4,
+                size[0],
+                size[1],
+                size[2],
+                bytes.len()
+            );
+        }
+
+        let data = bytes
+            .chunks_exact(4)
+            .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+            .collect();
+        Ok(Self::new(bounds, size, data, scale))
+    }
+
+    fn voxel(&self, x: usize, y: usize, z: usize) -> f64 {
+        let [nx, ny, _] = self.size;
+        self.data[(z * ny + y) * nx + x] as f64
+    }
+}
+
+impl Density for GridDensity {
+    fn density(&self, p: &Point3) -> f64 {
+        // 体素的值位于体素中心
+        let mut index = [0; 3];
+        let mut frac = [0.0; 3];
+        for axis in 0..3 {
+            let (min, max) = (self.bounds.min[axis], self.bounds.max[axis]);
+            let x = (p[axis] - min) / (max - min);
+            if !(0.0..=1.0).contains(&x) {
+                return 0.0;
+            }
+
+            let n = self.size[axis];
+            let x = (x * n as f64 - 0.5).clamp(0.0, (n - 1) as f64);
+            index[axis] = (x as usize).min(n.saturating_sub(2));
+            frac[axis] = x - index[axis] as f64;
+        }
+
+        let mut acc = 0.0;
+        for corner in 0..8 {
+            let mut weight = 1.0;
+            let mut at = index;
+            for axis in 0..3 {
+                match corner >> axis & 1 {
+                    0 => weight *= 1.0 - frac[axis],
+                    _ => {
+                        weight *= frac[axis];
+                        at[axis] = (at[axis] + 1).min(self.size[axis] - 1);
+                    }
+                }
+            }
+            acc += weight * self.voxel(at[0], at[1], at[2]);
+        }
+
+        acc * self.scale
+    }
+
+    fn max_density(&self) -> f64 {
+        self.max
+    }
+}
+
+// 密度不均匀的参与介质
+// 用 delta tracking 无偏地采样散射位置, 用 ratio tracking 估计透射率
+pub struct HeterogeneousMedium<T, D> {
+    boundary: T,
+    density: D,
+    phase: Arc<dyn Material>,
+}
+
+impl<T: Hittable, D: Density> HeterogeneousMedium<T, D> {
+    pub fn new(boundary: T, density: D, phase: Arc<dyn Material>) -> Self {
+        Self {
+            boundary,
+            density,
+            phase,
+        }
+    }
+}
+
+impl<T: Hittable, D: Density> Hittable for HeterogeneousMedium<T, D> {
+    fn hit(&self, r: &Ray, min: f64, max: f64) -> Option<Record<'_>> {
+        let (t0, t1) = interval(&self.boundary, r, min, max)?;
+        let majorant = self.density.max_density();
+        if majorant <= 0.0 {
+            return None;
+        }
+
+        // 按主密度采样候选碰撞点, 以 density / majorant 的概率接受为真实碰撞,
+        // 否则是虚拟碰撞, 继续向前走
+        let step = 1.0 / (majorant * r.direction().length());
+        let mut t = t0;
+        loop {
+            t -= (1.0 - random::random_double()).ln() * step;
+            if t >= t1 {
+                return None;
+            }
+            if random::random_double() * majorant < self.density.density(&r.at(t)) {
+                break;
+            }
+        }
+
+        let normal = Vec3::new(1.0, 0.0, 0.0);
+        let mut rec = Record::new(r, t, normal, (0.0, 0.0), self.phase.as_ref());
+        rec.front_face = true;
+        Some(rec)
+    }
+
+    fn hit_surface(&self, _r: &Ray, _min: f64, _max: f64) -> Option<Record<'_>> {
+        None
+    }
+
+    // 用 ratio tracking 估计光线在 [min, max] 区间内穿过介质而不发生碰撞的概率
+    fn transmittance(&self, r: &Ray, min: f64, max: f64) -> f64 {
+        let Some((t0, t1)) = interval(&self.boundary, r, min, max) else {
+            return 1.0;
+        };
+        let majorant = self.density.max_density();
+        if majorant <= 0.0 {
+            return 1.0;
+        }
+
+        // 按主密度采样虚拟碰撞点, 每个点乘上不发生真实碰撞的比例
+        let step = 1.0 / (majorant * r.direction().length());
+        let mut tr = 1.0;
+        let mut t = t0;
+        loop {
+            t -= (1.0 - random::random_double()).ln() * step;
+            if t >= t1 {
+                return tr;
+            }
+            tr *= 1.0 - self.density.density(&r.at(t)) / majorant;
+        }
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        self.boundary.bounding_box()
+    }
+}
@@ -0,0 +1,109 @@
+use std::sync::Arc;
+
+use crate::{
+    aabb::Aabb,
+    hit::{Hittable, Record},
+    material::Material,
+    ray::Ray,
+    triangle,
+    vec3::{Point3, Vec3},
+};
+
+// 三角形网格, 所有面共享顶点/法线/纹理坐标缓冲, 每个面只保存下标
+pub struct Mesh {
+    pub positions: Vec<Point3>,
+    pub normals: Vec<Vec3>,
+    pub uvs: Vec<(f64, f64)>,
+    pub faces: Vec<Face>,
+    pub materials: Vec<Arc<dyn Material>>,
+}
+
+pub struct Face {
+    pub positions: [usize; 3],       // 顶点下标
+    pub normals: Option<[usize; 3]>, // 顶点法线下标, 没有时使用几何法线
+    pub uvs: Option<[usize; 3]>,     // 顶点纹理坐标下标
+    pub material: usize,             // 材质下标
+}
+
+impl Mesh {
+    pub fn new(materials: Vec<Arc<dyn Material>>) -> Self {
+        Self {
+            positions: vec![],
+            normals: vec![],
+            uvs: vec![],
+            faces: vec![],
+            materials,
+        }
+    }
+
+    // 把网格拆成一个个三角形, 方便放进 HittableList 或者 BvhNode
+    pub fn triangles(mesh: &Arc<Self>) -> impl Iterator<Item = MeshTriangle> + '_ {
+        (0..mesh.faces.len()).map(|face| MeshTriangle {
+            mesh: mesh.clone(),
+            face,
+        })
+    }
+
+    fn vertices(&self, face: &Face) -> [Point3; 3] {
+        face.positions.map(|i| self.positions[i])
+    }
+}
+
+// 网格中的一个面
+pub struct MeshTriangle {
+    mesh: Arc<Mesh>,
+    face: usize,
+}
+
+impl Hittable for MeshTriangle {
+    fn hit(&self, r: &Ray, min: f64, max: f64) -> Option<Record<'_>> {
+        let face = &self.mesh.faces[self.face];
+        let [v0, v1, v2] = self.mesh.vertices(face);
+        let (t, b1, b2) = triangle::intersect(r, &v0, &v1, &v2, min, max)?;
+
+        let material = self.mesh.materials[face.material].as_ref();
+        let outward_normal = Vec3::cross(&(v1 - v0), &(v2 - v0)).unit();
+
+        // 有顶点纹理坐标时按重心坐标插值, 否则直接用重心坐标
+        // 切向量由两条边和纹理坐标的差解出, 纹理坐标退化时同样退回到两条边
+        let b0 = 1.0 - b1 - b2;
+        let (e1, e2) = (v1 - v0, v2 - v0);
+        let (uv, (dpdu, dpdv)) = match face.uvs {
+            Some([t0, t1, t2]) => {
+                let uvs = &self.mesh.uvs;
+                let u = b0 * uvs[t0].0 + b1 * uvs[t1].0 + b2 * uvs[t2].0;
+                let v = b0 * uvs[t0].1 + b1 * uvs[t1].1 + b2 * uvs[t2].1;
+
+                let (du1, dv1) = (uvs[t1].0 - uvs[t0].0, uvs[t1].1 - uvs[t0].1);
+                let (du2, dv2) = (uvs[t2].0 - uvs[t0].0, uvs[t2].1 - uvs[t0].1);
+                let det = du1 * dv2 - dv1 * du2;
+                let tangents = match det.abs() > 1e-12 {
+                    true => ((dv2 * e1 - dv1 * e2) / det, (du1 * e2 - du2 * e1) / det),
+                    false => (e1, e2),
+                };
+                ((u, v), tangents)
+            }
+            None => ((b1, b2), (e1, e2)),
+        };
+
+        let mut rec = Record::new(r, t, outward_normal, uv, material).with_tangents(dpdu, dpdv);
+
+        // 用重心坐标插值顶点法线作为着色法线, 朝向与几何法线保持一致
+        if let Some([n0, n1, n2]) = face.normals {
+            let normals = &self.mesh.normals;
+            let n = b0 * normals[n0] + b1 * normals[n1] + b2 * normals[n2];
+            let n = n.unit();
+            rec.normal = match Vec3::dot(&n, &rec.normal) < 0.0 {
+                true => -n,
+                false => n,
+            };
+        }
+
+        Some(rec)
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let face = &self.mesh.faces[self.face];
+        triangle::bounding_box(&self.mesh.vertices(face))
+    }
+}
@@ -0,0 +1,144 @@
+use std::f64::consts::PI;
+
+use crate::{
+    random,
+    vec3::{Color, Vec3},
+};
+
+// GGX (Trowbridge-Reitz) 微表面分布, 所有方向都在局部坐标系中, z 轴是宏观法线
+#[derive(Clone, Copy, Debug)]
+pub struct Ggx {
+    alpha: f64,
+}
+
+impl Ggx {
+    // 和大多数 PBR 工具一样, alpha = roughness²
+    pub fn new(roughness: f64) -> Self {
+        let r = roughness.clamp(0.0, 1.0);
+        Self {
+            alpha: (r * r).max(1e-4),
+        }
+    }
+
+    pub fn alpha(&self) -> f64 {
+        self.alpha
+    }
+
+    // 足够光滑时退化为镜面, 按镜面处理避免数值问题
+    pub fn is_smooth(&self) -> bool {
+        self.alpha < 1e-3
+    }
+
+    // 微表面法线 h 的分布 D(h)
+    pub fn d(&self, h: &Vec3) -> f64 {
+        if h.z() <= 0.0 {
+            return 0.0;
+        }
+
+        let a2 = self.alpha * self.alpha;
+        let cos2 = h.z() * h.z();
+        let denom = cos2 * (a2 - 1.0) + 1.0;
+        a2 / (PI * denom * denom)
+    }
+
+    // Smith 遮蔽函数中的 Λ(w)
+    fn lambda(&self, w: &Vec3) -> f64 {
+        let cos2 = w.z() * w.z();
+        if cos2 <= 0.0 {
+            return f64::INFINITY;
+        }
+
+        let tan2 = (w.x() * w.x() + w.y() * w.y()) / cos2;
+        0.5 * (-1.0 + (1.0 + self.alpha * self.alpha * tan2).sqrt())
+    }
+
+    // 单个方向的遮蔽 G1(w)
+    pub fn g1(&self, w: &Vec3) -> f64 {
+        1.0 / (1.0 + self.lambda(w))
+    }
+
+    // 高度相关的遮蔽-阴影 G2(wo, wi)
+    pub fn g2(&self, wo: &Vec3, wi: &Vec3) -> f64 {
+        1.0 / (1.0 + self.lambda(wo) + self.lambda(wi))
+    }
+
+    // 从 wo 看到的可见法线的概率密度 D_wo(h) = G1(wo) max(0, wo·h) D(h) / wo.z
+    pub fn pdf(&self, wo: &Vec3, h: &Vec3) -> f64 {
+        let cos = Vec3::dot(wo, h).max(0.0);
+        self.g1(wo) * cos * self.d(h) / wo.z().abs()
+    }
+
+    // 反射到 wi 方向时的微表面法线, D G2 / (4 cosθo) 以及采样到 wi 的概率密度
+    // 乘上菲涅尔项就是 BRDF 乘以余弦项, wi 在下半球时返回 None
+    pub fn reflection(&self, wo: &Vec3, wi: &Vec3) -> Option<(Vec3, f64, f64)> {
+        if wo.z() <= 0.0 || wi.z() <= 0.0 {
+            return None;
+        }
+
+        let h = (*wo + *wi).unit();
+        let value = self.d(&h) * self.g2(wo, wi) / (4.0 * wo.z());
+        let pdf = self.pdf(wo, &h) / (4.0 * Vec3::dot(wo, &h));
+        Some((h, value, pdf))
+    }
+
+    // 按可见法线分布采样微表面法线 (Heitz 2018)
+    pub fn sample(&self, wo: &Vec3) -> Vec3 {
+        // 拉伸到 alpha = 1 的半球上
+        let vh = Vec3::new(self.alpha * wo.x(), self.alpha * wo.y(), wo.z()).unit();
+        let len2 = vh.x() * vh.x() + vh.y() * vh.y();
+        let t1 = match len2 > 0.0 {
+            true => Vec3::new(-vh.y(), vh.x(), 0.0) / len2.sqrt(),
+            false => Vec3::new(1.0, 0.0, 0.0),
+        };
+        let t2 = Vec3::cross(&vh, &t1);
+
+        // 在投影后的圆盘上采样
+        let r = random::random_double().sqrt();
+        let phi = 2.0 * PI * random::random_double();
+        let p1 = r * phi.cos();
+        let p2 = r * phi.sin();
+        let s = 0.5 * (1.0 + vh.z());
+        let p2 = (1.0 - s) * (1.0 - p1 * p1).sqrt() + s * p2;
+
+        // 投影回半球再反拉伸
+        let nh = p1 * t1 + p2 * t2 + (1.0 - p1 * p1 - p2 * p2).max(0.0).sqrt() * vh;
+        Vec3::new(self.alpha * nh.x(), self.alpha * nh.y(), nh.z().max(1e-6)).unit()
+    }
+}
+
+// 电介质的菲涅尔反射率, eta 是透射侧与入射侧折射率之比, 发生全反射时为 1
+pub fn fresnel_dielectric(cos_i: f64, eta: f64) -> f64 {
+    let cos_i = cos_i.clamp(-1.0, 1.0).abs();
+    let sin2_t = (1.0 - cos_i * cos_i) / (eta * eta);
+    if sin2_t >= 1.0 {
+        return 1.0;
+    }
+
+    let cos_t = (1.0 - sin2_t).sqrt();
+    let rs = (cos_i - eta * cos_t) / (cos_i + eta * cos_t);
+    let rp = (eta * cos_i - cos_t) / (eta * cos_i + cos_t);
+    0.5 * (rs * rs + rp * rp)
+}
+
+// Schlick 近似, f0 是垂直入射时的反射率
+pub fn fresnel_schlick(f0: Color, cos: f64) -> Color {
+    let m = (1.0 - cos.clamp(0.0, 1.0)).powi(5);
+    f0 + m * (Color::new(1.0, 1.0, 1.0) - f0)
+}
+
+// 以 h 为法线的镜面反射
+pub fn reflect(wo: &Vec3, h: &Vec3) -> Vec3 {
+    2.0 * Vec3::dot(wo, h) * *h - *wo
+}
+
+// 以 h 为法线的折射, wo 与 h 在同一侧, eta 是透射侧与入射侧折射率之比, 全反射时返回 None
+pub fn refract(wo: &Vec3, h: &Vec3, eta: f64) -> Option<Vec3> {
+    let cos_i = Vec3::dot(wo, h);
+    let sin2_t = (1.0 - cos_i * cos_i).max(0.0) / (eta * eta);
+    if sin2_t >= 1.0 {
+        return None;
+    }
+
+    let cos_t = (1.0 - sin2_t).sqrt();
+    Some(-*wo / eta + (cos_i / eta - cos_t) * *h)
+}
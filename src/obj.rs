@@ -0,0 +1,343 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::SplitWhitespace;
+use std::sync::Arc;
+
+use anyhow::{anyhow, bail, Context, Result};
+
+use crate::{
+    material::{Dielectric, DiffuseLight, Lambertian, Material, Metal, Principled},
+    mesh::{Face, Mesh},
+    texture::ImageTexture,
+    vec3::{Color, Vec3},
+};
+
+// 读取 Wavefront OBJ 文件, 以及其中 mtllib 引用的 MTL 材质库
+// 多边形面按扇形拆成三角形, 没有指定材质的面使用灰色漫反射材质
+pub fn load<P: AsRef<Path>>(path: P) -> Result<Mesh> {
+    let path = path.as_ref();
+    let src = fs::read_to_string(path).with_context(|| format!("read {}", path.display()))?;
+
+    let mut mesh = Mesh::new(vec![Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)))]);
+    let mut library = HashMap::new();
+    let mut material = 0;
+    let mut indices = HashMap::new();
+
+    for (n, line) in src.lines().enumerate() {
+        let err = || format!("{}:{}", path.display(), n + 1);
+        let mut args = line.split_whitespace();
+        match args.next() {
+            Some("v") => mesh
+                .positions
+                .push(parse_vec3(&mut args).with_context(err)?),
+            Some("vn") => mesh.normals.push(parse_vec3(&mut args).with_context(err)?),
+            Some("vt") => {
+                let u = parse_f64(args.next()).with_context(err)?;
+                let v = args.next().map_or(Ok(0.0), |v| parse_f64(Some(v)));
+                mesh.uvs.push((u, v.with_context(err)?));
+            }
+            Some("f") => {
+                let vertices = args
+                    .map(|arg| parse_vertex(arg, &mesh))
+                    .collect::<Result<Vec<_>>>()
+                    .with_context(err)?;
+                if vertices.len() < 3 {
+                    return Err(anyhow!("face needs at least 3 vertices")).with_context(err);
+                }
+
+                for i in 1..vertices.len() - 1 {
+                    let [a, b, c] = [vertices[0], vertices[i], vertices[i + 1]];
+                    mesh.faces.push(Face {
+                        positions: [a.0, b.0, c.0],
+                        uvs: a.1.zip(b.1).zip(c.1).map(|((a, b), c)| [a, b, c]),
+                        normals: a.2.zip(b.2).zip(c.2).map(|((a, b), c)| [a, b, c]),
+                        material,
+                    });
+                }
+            }
+            Some("mtllib") => {
+                let dir = path.parent().unwrap_or(Path::new(""));
+                for file in args {
+                    library.extend(load_mtl(dir.join(file)).with_context(err)?);
+                }
+            }
+            Some("usemtl") => {
+                let name = args.next().ok_or_else(|| anyhow!("missing material name"));
+                let name = name.with_context(err)?;
+                material = match indices.get(name) {
+                    Some(&i) => i,
+                    None => {
+                        let m = library
+                            .get(name)
+                            .ok_or_else(|| anyhow!("unknown material '{}'", name))
+                            .with_context(err)?;
+                        mesh.materials.push(m.clone());
+                        indices.insert(name.to_string(), mesh.materials.len() - 1);
+                        mesh.materials.len() - 1
+                    }
+                };
+            }
+            // 注释, 空行, 对象/分组/平滑组等不影响渲染的语句
+            _ => {}
+        }
+    }
+
+    Ok(mesh)
+}
+
+// 读取 MTL 材质库, 按照名字映射到已有的材质
+pub fn load_mtl<P: AsRef<Path>>(path: P) -> Result<HashMap<String, Arc<dyn Material>>> {
+    let path = path.as_ref();
+    let src = fs::read_to_string(path).with_context(|| format!("read {}", path.display()))?;
+
+    let dir = path.parent().unwrap_or(Path::new(""));
+
+    let mut materials = vec![];
+    for (n, line) in src.lines().enumerate() {
+        let err = || format!("{}:{}", path.display(), n + 1);
+        let mut args = line.split_whitespace();
+        let cmd = args.next();
+        if cmd == Some("newmtl") {
+            let name = args.next().ok_or_else(|| anyhow!("missing material name"));
+            materials.push((name.with_context(err)?.to_string(), Mtl::default()));
+            continue;
+        }
+
+        let Some(cmd) = cmd.filter(|cmd| !cmd.starts_with('#')) else {
+            continue;
+        };
+        let Some((_, mtl)) = materials.last_mut() else {
+            return Err(anyhow!("'{}' before newmtl", cmd)).with_context(err);
+        };
+        match cmd {
+            "Kd" => mtl.kd = parse_vec3(&mut args).with_context(err)?,
+            "Ks" => mtl.ks = parse_vec3(&mut args).with_context(err)?,
+            "Ke" => mtl.ke = parse_vec3(&mut args).with_context(err)?,
+            "Ns" => mtl.ns = parse_f64(args.next()).with_context(err)?,
+            "Ni" => mtl.ni = parse_f64(args.next()).with_context(err)?,
+            "d" => mtl.d = parse_f64(args.next()).with_context(err)?,
+            "Tr" => mtl.d = 1.0 - parse_f64(args.next()).with_context(err)?,
+            "illum" => mtl.illum = parse_f64(args.next()).with_context(err)? as u32,
+            // PBR 扩展
+            "Pr" => mtl.pbr.roughness = Some(parse_f64(args.next()).with_context(err)?),
+            "Pm" => mtl.pbr.metallic = Some(parse_f64(args.next()).with_context(err)?),
+            "Ps" => mtl.pbr.sheen = Some(parse_f64(args.next()).with_context(err)?),
+            "Pc" => mtl.pbr.clearcoat = Some(parse_f64(args.next()).with_context(err)?),
+            "Pcr" => mtl.pbr.clearcoat_roughness = Some(parse_f64(args.next()).with_context(err)?),
+            // 贴图选项写在文件名前面, 只取最后的文件名
+            "map_Kd" => {
+                let file = args.last().ok_or_else(|| anyhow!("missing texture file"));
+                mtl.map_kd = Some(dir.join(file.with_context(err)?));
+            }
+            // 贴图等暂不支持的属性
+            _ => {}
+        }
+    }
+
+    materials
+        .into_iter()
+        .map(|(name, mtl)| Ok((name, mtl.material()?)))
+        .collect()
+}
+
+// MTL 中与渲染相关的参数
+struct Mtl {
+    kd: Color, // 漫反射颜色
+    ks: Color, // 镜面反射颜色
+    ke: Color, // 自发光颜色
+    ns: f64,   // 高光指数
+    ni: f64,   // 折射率
+    d: f64,    // 不透明度
+    illum: u32,
+
+    map_kd: Option<PathBuf>, // 漫反射贴图
+    pbr: Pbr,
+}
+
+// MTL 的 PBR 扩展参数, 出现任意一个时使用 Principled 材质
+#[derive(Default)]
+struct Pbr {
+    roughness: Option<f64>,           // Pr
+    metallic: Option<f64>,            // Pm
+    sheen: Option<f64>,               // Ps
+    clearcoat: Option<f64>,           // Pc
+    clearcoat_roughness: Option<f64>, // Pcr
+}
+
+impl Pbr {
+    fn is_empty(&self) -> bool {
+        [
+            self.roughness,
+            self.metallic,
+            self.sheen,
+            self.clearcoat,
+            self.clearcoat_roughness,
+        ]
+        .iter()
+        .all(Option::is_none)
+    }
+}
+
+impl Default for Mtl {
+    fn default() -> Self {
+        Self {
+            kd: Color::new(0.8, 0.8, 0.8),
+            ks: Color::default(),
+            ke: Color::default(),
+            ns: 0.0,
+            ni: 1.0,
+            d: 1.0,
+            illum: 2,
+            map_kd: None,
+            pbr: Pbr::default(),
+        }
+    }
+}
+
+impl Mtl {
+    fn material(&self) -> Result<Arc<dyn Material>> {
+        let luminance = |c: &Color| c.x().max(c.y()).max(c.z());
+
+        if luminance(&self.ke) <= 0.0 && !self.pbr.is_empty() {
+            return self.principled();
+        }
+
+        // 有自发光的当作光源, 透明或者 illum 指定折射的当作电介质,
+        // illum 指定反射或者镜面反射比漫反射更强的当作金属, 其余都是漫反射
+        Ok(if luminance(&self.ke) > 0.0 {
+            Arc::new(DiffuseLight::new(self.ke))
+        } else if self.d < 1.0 || matches!(self.illum, 4 | 6 | 7 | 9) {
+            Arc::new(Dielectric::new(self.ni.max(1.0)))
+        } else if matches!(self.illum, 3 | 5) || luminance(&self.ks) > luminance(&self.kd) {
+            // 高光指数越大表面越光滑, 按照 Blinn-Phong 与粗糙度的对应关系换算成模糊度
+            let fuzz = (2.0 / (self.ns + 2.0)).sqrt();
+            Arc::new(Metal::new(self.ks, fuzz))
+        } else if let Some(map_kd) = &self.map_kd {
+            Arc::new(Lambertian::with_texture(Arc::new(ImageTexture::open(
+                map_kd,
+            )?)))
+        } else {
+            Arc::new(Lambertian::new(self.kd))
+        })
+    }
+
+    // 透明度换算成透射比例, 清漆的粗糙度换算成光泽度, 没有写 Ni 时用默认的折射率
+    fn principled(&self) -> Result<Arc<dyn Material>> {
+        let base = match &self.map_kd {
+            Some(map_kd) => Principled::with_texture(Arc::new(ImageTexture::open(map_kd)?)),
+            None => Principled::new(self.kd),
+        };
+        let pbr = &self.pbr;
+        Ok(Arc::new(Principled {
+            metallic: pbr.metallic.unwrap_or(0.0),
+            roughness: pbr.roughness.unwrap_or(base.roughness),
+            sheen: pbr.sheen.unwrap_or(0.0),
+            clearcoat: pbr.clearcoat.unwrap_or(0.0),
+            clearcoat_gloss: 1.0 - pbr.clearcoat_roughness.unwrap_or(0.0),
+            transmission: (1.0 - self.d).clamp(0.0, 1.0),
+            ior: match self.ni > 1.0 {
+                true => self.ni,
+                false => base.ior,
+            },
+            ..base
+        }))
+    }
+}
+
+// 解析面的一个顶点 v, v/vt, v//vn 或者 v/vt/vn, 下标从 1 开始, 负数表示倒数
+fn parse_vertex(arg: &str, mesh: &Mesh) -> Result<(usize, Option<usize>, Option<usize>)> {
+    let index = |s: Option<&str>, len: usize| -> Result<Option<usize>> {
+        let Some(s) = s.filter(|s| !s.is_empty()) else {
+            return Ok(None);
+        };
+        let i: i64 = s
+            .parse()
+            .with_context(|| format!("invalid index '{}'", s))?;
+        let i = if i < 0 { len as i64 + i } else { i - 1 };
+        if i < 0 || i >= len as i64 {
+            bail!("index '{}' out of range", s);
+        }
+        Ok(Some(i as usize))
+    };
+
+    let mut parts = arg.split('/');
+    let v = index(parts.next(), mesh.positions.len())?;
+    let vt = index(parts.next(), mesh.uvs.len())?;
+    let vn = index(parts.next(), mesh.normals.len())?;
+    Ok((v.ok_or_else(|| anyhow!("missing vertex index"))?, vt, vn))
+}
+
+fn parse_f64(arg: Option<&str>) -> Result<f64> {
+    let arg = arg.ok_or_else(|| anyhow!("missing number"))?;
+    arg.parse()
+        .with_context(|| format!("invalid number '{}'", arg))
+}
+
+fn parse_vec3(args: &mut SplitWhitespace) -> Result<Vec3> {
+    let x = parse_f64(args.next())?;
+    let y = parse_f64(args.next())?;
+    let z = parse_f64(args.next())?;
+    Ok(Vec3::new(x, y, z))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use super::*;
+
+    // 3 个顶点, 2 个纹理坐标, 1 条法线
+    fn mesh() -> Mesh {
+        let mut mesh = Mesh::new(vec![]);
+        mesh.positions = vec![Vec3::default(); 3];
+        mesh.uvs = vec![(0.0, 0.0); 2];
+        mesh.normals = vec![Vec3::new(0.0, 0.0, 1.0)];
+        mesh
+    }
+
+    #[test]
+    fn vertex_forms() {
+        let mesh = mesh();
+        assert_eq!(parse_vertex("1", &mesh).unwrap(), (0, None, None));
+        assert_eq!(parse_vertex("2/1", &mesh).unwrap(), (1, Some(0), None));
+        assert_eq!(parse_vertex("3//1", &mesh).unwrap(), (2, None, Some(0)));
+        assert_eq!(parse_vertex("3/2/1", &mesh).unwrap(), (2, Some(1), Some(0)));
+    }
+
+    #[test]
+    fn negative_indices() {
+        let mesh = mesh();
+        assert_eq!(parse_vertex("-1", &mesh).unwrap(), (2, None, None));
+        assert_eq!(
+            parse_vertex("-3/-2/-1", &mesh).unwrap(),
+            (0, Some(0), Some(0))
+        );
+    }
+
+    #[test]
+    fn out_of_range() {
+        let mesh = mesh();
+        for arg in ["0", "4", "-4", "1/3", "1/-3", "1//2", "1//0"] {
+            assert!(parse_vertex(arg, &mesh).is_err(), "{}", arg);
+        }
+        for arg in ["", "/1", "x", "1/x", "1.5"] {
+            assert!(parse_vertex(arg, &mesh).is_err(), "{}", arg);
+        }
+    }
+
+    // 四边形按扇形拆成两个三角形, 负数下标相对当前已读入的顶点
+    #[test]
+    fn load_polygon() {
+        let path = env::temp_dir().join(format!("rtwk-obj-test-{}.obj", std::process::id()));
+        let src = "v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\nf -4 -3 -2 -1\n";
+        fs::write(&path, src).unwrap();
+        let mesh = load(&path);
+        fs::remove_file(&path).unwrap();
+
+        let mesh = mesh.unwrap();
+        assert_eq!(mesh.positions.len(), 4);
+        let faces: Vec<_> = mesh.faces.iter().map(|f| f.positions).collect();
+        assert_eq!(faces, [[0, 1, 2], [0, 2, 3]]);
+    }
+}
@@ -0,0 +1,107 @@
+use std::f64::consts::PI;
+
+use crate::{
+    hit::Hittable,
+    random,
+    vec3::{Onb, Point3, Vec3},
+};
+
+// 方向上的概率密度函数 (对立体角)
+pub trait Pdf {
+    // 方向 dir 的概率密度
+    fn value(&self, dir: &Vec3) -> f64;
+
+    // 按该分布随机生成一个方向
+    fn generate(&self) -> Vec3;
+}
+
+// 整个球面上均匀分布
+pub struct SpherePdf;
+
+impl Pdf for SpherePdf {
+    fn value(&self, _: &Vec3) -> f64 {
+        1.0 / (4.0 * PI)
+    }
+
+    fn generate(&self) -> Vec3 {
+        Vec3::random_unit_vector()
+    }
+}
+
+// 法线所在半球上按余弦分布
+pub struct CosinePdf {
+    uvw: Onb,
+}
+
+impl CosinePdf {
+    pub fn new(normal: &Vec3) -> Self {
+        Self {
+            uvw: Onb::new(normal),
+        }
+    }
+}
+
+impl Pdf for CosinePdf {
+    fn value(&self, dir: &Vec3) -> f64 {
+        let cosine = Vec3::dot(&dir.unit(), self.uvw.w());
+        cosine.max(0.0) / PI
+    }
+
+    fn generate(&self) -> Vec3 {
+        self.uvw.local(&Vec3::random_cosine_direction())
+    }
+}
+
+// 从 origin 看向物体的方向, 由物体的 pdf_value 和 random 决定
+pub struct HittablePdf<'a, H: ?Sized> {
+    objects: &'a H,
+    origin: Point3,
+}
+
+impl<'a, H: Hittable + ?Sized> HittablePdf<'a, H> {
+    pub fn new(objects: &'a H, origin: Point3) -> Self {
+        Self { objects, origin }
+    }
+}
+
+impl<H: Hittable + ?Sized> Pdf for HittablePdf<'_, H> {
+    fn value(&self, dir: &Vec3) -> f64 {
+        self.objects.pdf_value(&self.origin, dir)
+    }
+
+    fn generate(&self) -> Vec3 {
+        self.objects.random(&self.origin)
+    }
+}
+
+// 两个分布的混合, 以 weight 的概率从第一个分布生成
+pub struct MixturePdf<'a> {
+    p: [&'a dyn Pdf; 2],
+    weight: f64,
+}
+
+impl<'a> MixturePdf<'a> {
+    pub fn new(p0: &'a dyn Pdf, p1: &'a dyn Pdf) -> Self {
+        Self::with_weight(p0, p1, 0.5)
+    }
+
+    pub fn with_weight(p0: &'a dyn Pdf, p1: &'a dyn Pdf, weight: f64) -> Self {
+        Self {
+            p: [p0, p1],
+            weight,
+        }
+    }
+}
+
+impl Pdf for MixturePdf<'_> {
+    fn value(&self, dir: &Vec3) -> f64 {
+        self.weight * self.p[0].value(dir) + (1.0 - self.weight) * self.p[1].value(dir)
+    }
+
+    fn generate(&self) -> Vec3 {
+        match random::random_double() < self.weight {
+            true => self.p[0].generate(),
+            false => self.p[1].generate(),
+        }
+    }
+}
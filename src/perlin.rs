@@ -0,0 +1,105 @@
+use rand::{rngs::SmallRng, seq::SliceRandom, Rng, SeedableRng};
+
+use crate::vec3::{Point3, Vec3};
+
+const POINT_COUNT: usize = 256;
+
+// Perlin 噪声, 整数格点上放置随机梯度向量, 格点之间用 Hermite 平滑后做三线性插值
+pub struct Perlin {
+    ranvec: Vec<Vec3>,
+    perm_x: Vec<usize>,
+    perm_y: Vec<usize>,
+    perm_z: Vec<usize>,
+}
+
+impl Perlin {
+    // 相同的种子生成相同的噪声
+    pub fn new(seed: u64) -> Self {
+        let mut rng = SmallRng::seed_from_u64(seed);
+
+        let ranvec = (0..POINT_COUNT)
+            .map(|_| {
+                let x = rng.gen_range(-1.0..1.0);
+                let y = rng.gen_range(-1.0..1.0);
+                let z = rng.gen_range(-1.0..1.0);
+                Vec3::new(x, y, z).unit()
+            })
+            .collect();
+
+        let mut perm = || {
+            let mut p = (0..POINT_COUNT).collect::<Vec<_>>();
+            p.shuffle(&mut rng);
+            p
+        };
+
+        Self {
+            ranvec,
+            perm_x: perm(),
+            perm_y: perm(),
+            perm_z: perm(),
+        }
+    }
+
+    // 取值范围大致在 [-1, 1]
+    pub fn noise(&self, p: &Point3) -> f64 {
+        let u = p.x() - p.x().floor();
+        let v = p.y() - p.y().floor();
+        let w = p.z() - p.z().floor();
+
+        let i = p.x().floor() as i64;
+        let j = p.y().floor() as i64;
+        let k = p.z().floor() as i64;
+
+        let mut c = [[[Vec3::default(); 2]; 2]; 2];
+        for (di, c) in c.iter_mut().enumerate() {
+            for (dj, c) in c.iter_mut().enumerate() {
+                for (dk, c) in c.iter_mut().enumerate() {
+                    let x = self.perm_x[((i + di as i64) & 255) as usize];
+                    let y = self.perm_y[((j + dj as i64) & 255) as usize];
+                    let z = self.perm_z[((k + dk as i64) & 255) as usize];
+                    *c = self.ranvec[x ^ y ^ z];
+                }
+            }
+        }
+
+        Self::interp(&c, u, v, w)
+    }
+
+    // 湍流: 多个频率倍增, 振幅减半的噪声叠加
+    pub fn turb(&self, p: &Point3, depth: usize) -> f64 {
+        let mut acc = 0.0;
+        let mut p = *p;
+        let mut weight = 1.0;
+
+        for _ in 0..depth {
+            acc += weight * self.noise(&p);
+            weight *= 0.5;
+            p *= 2.0;
+        }
+
+        acc.abs()
+    }
+
+    fn interp(c: &[[[Vec3; 2]; 2]; 2], u: f64, v: f64, w: f64) -> f64 {
+        // Hermite 三次平滑, 消除格点处的马赫带
+        let uu = u * u * (3.0 - 2.0 * u);
+        let vv = v * v * (3.0 - 2.0 * v);
+        let ww = w * w * (3.0 - 2.0 * w);
+
+        let mut acc = 0.0;
+        for (i, c) in c.iter().enumerate() {
+            for (j, c) in c.iter().enumerate() {
+                for (k, c) in c.iter().enumerate() {
+                    let (fi, fj, fk) = (i as f64, j as f64, k as f64);
+                    let weight = Vec3::new(u - fi, v - fj, w - fk);
+                    acc += (fi * uu + (1.0 - fi) * (1.0 - uu))
+                        * (fj * vv + (1.0 - fj) * (1.0 - vv))
+                        * (fk * ww + (1.0 - fk) * (1.0 - ww))
+                        * Vec3::dot(c, &weight);
+                }
+            }
+        }
+
+        acc
+    }
+}
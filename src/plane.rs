@@ -0,0 +1,69 @@
+use std::sync::Arc;
+
+use crate::{
+    aabb::Aabb,
+    hit::{Hittable, Record},
+    material::Material,
+    ray::Ray,
+    vec3::{Point3, Vec3},
+};
+
+// 无限大的平面, 经过 point 且法线为 normal
+// 纹理坐标是撞击点在平面内两个正交方向上的坐标, 不做归一化, 图片纹理会平铺
+pub struct Plane {
+    pub point: Point3,
+    pub normal: Vec3,
+    pub material: Arc<dyn Material>,
+
+    tangent: Vec3,   // 平面内的 u 方向
+    bitangent: Vec3, // 平面内的 v 方向
+}
+
+impl Plane {
+    pub fn new(point: Point3, normal: Vec3, material: Arc<dyn Material>) -> Self {
+        let normal = normal.unit();
+
+        // 任选一个不与法线平行的轴, 叉乘得到平面内的两个方向
+        let axis = match normal.x().abs() > 0.9 {
+            true => Vec3::new(0.0, 1.0, 0.0),
+            false => Vec3::new(1.0, 0.0, 0.0),
+        };
+        let bitangent = Vec3::cross(&normal, &axis).unit();
+        let tangent = Vec3::cross(&bitangent, &normal);
+
+        Self {
+            point,
+            normal,
+            material,
+            tangent,
+            bitangent,
+        }
+    }
+}
+
+impl Hittable for Plane {
+    fn hit(&self, r: &Ray, min: f64, max: f64) -> Option<Record<'_>> {
+        let denom = Vec3::dot(&self.normal, r.direction());
+        if denom.abs() < 1e-8 {
+            return None;
+        }
+
+        let t = Vec3::dot(&self.normal, &(self.point - *r.origin())) / denom;
+        if t < min || max < t {
+            return None;
+        }
+
+        let planar = r.at(t) - self.point;
+        let uv = (
+            Vec3::dot(&planar, &self.tangent),
+            Vec3::dot(&planar, &self.bitangent),
+        );
+        let rec = Record::new(r, t, self.normal, uv, self.material.as_ref());
+        Some(rec.with_tangents(self.tangent, self.bitangent))
+    }
+
+    // 无限大的平面没有包围盒, 不能放进 BvhNode
+    fn bounding_box(&self) -> Option<Aabb> {
+        None
+    }
+}
@@ -0,0 +1,132 @@
+use std::sync::Arc;
+
+use crate::{
+    aabb::Aabb,
+    hit::{Hittable, HittableList, Record},
+    material::Material,
+    random,
+    ray::Ray,
+    vec3::{Point3, Vec3},
+};
+
+// 平行四边形, 以 q 为一个角, u 和 v 为两条邻边
+// 平面内的点 p = q + alpha * u + beta * v, (alpha, beta) 同时作为纹理坐标
+pub struct Quad {
+    pub q: Point3,
+    pub u: Vec3,
+    pub v: Vec3,
+    pub material: Arc<dyn Material>,
+
+    normal: Vec3, // 单位法线 u x v
+    d: f64,       // 平面方程 normal · p = d
+    w: Vec3,      // 用于求平面坐标 alpha, beta 的辅助向量 n / (n · n)
+    area: f64,
+}
+
+impl Quad {
+    pub fn new(q: Point3, u: Vec3, v: Vec3, material: Arc<dyn Material>) -> Self {
+        let n = Vec3::cross(&u, &v);
+        let normal = n.unit();
+
+        Self {
+            q,
+            u,
+            v,
+            material,
+            normal,
+            d: Vec3::dot(&normal, &q),
+            w: n / Vec3::dot(&n, &n),
+            area: n.length(),
+        }
+    }
+}
+
+impl Hittable for Quad {
+    fn hit(&self, r: &Ray, min: f64, max: f64) -> Option<Record<'_>> {
+        // 光线与平面平行
+        let denom = Vec3::dot(&self.normal, r.direction());
+        if denom.abs() < 1e-8 {
+            return None;
+        }
+
+        let t = (self.d - Vec3::dot(&self.normal, r.origin())) / denom;
+        if t < min || max < t {
+            return None;
+        }
+
+        // 交点在平面上的坐标必须落在 [0, 1] x [0, 1] 以内
+        let planar = r.at(t) - self.q;
+        let alpha = Vec3::dot(&self.w, &Vec3::cross(&planar, &self.v));
+        let beta = Vec3::dot(&self.w, &Vec3::cross(&self.u, &planar));
+        if !(0.0..=1.0).contains(&alpha) || !(0.0..=1.0).contains(&beta) {
+            return None;
+        }
+
+        let material = self.material.as_ref();
+        let rec = Record::new(r, t, self.normal, (alpha, beta), material);
+        Some(rec.with_tangents(self.u, self.v))
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let diagonal1 = Aabb::new(self.q, self.q + self.u + self.v);
+        let diagonal2 = Aabb::new(self.q + self.u, self.q + self.v);
+        Some(Aabb::surrounding(&diagonal1, &diagonal2))
+    }
+
+    // 在面上按面积均匀采样, 换算到立体角要乘上 distance² / cosθ
+    fn pdf_value(&self, origin: &Point3, dir: &Vec3) -> f64 {
+        let r = Ray::new(*origin, *dir, 0.0);
+        let Some(rec) = self.hit(&r, 0.001, f64::INFINITY) else {
+            return 0.0;
+        };
+
+        let distance_squared = rec.t * rec.t * dir.length_square();
+        let cosine = (Vec3::dot(dir, &self.normal) / dir.length()).abs();
+        distance_squared / (cosine * self.area)
+    }
+
+    fn random(&self, origin: &Point3) -> Vec3 {
+        let alpha = random::random_double();
+        let beta = random::random_double();
+        self.q + alpha * self.u + beta * self.v - *origin
+    }
+}
+
+// 长方体, 由六个面组成, 各个面的法线都朝外
+pub struct BoxShape {
+    sides: HittableList<Quad>,
+}
+
+impl BoxShape {
+    // a 和 b 是长方体的两个对角
+    pub fn new(a: Point3, b: Point3, material: Arc<dyn Material>) -> Self {
+        let min = Point3::min(&a, &b);
+        let max = Point3::max(&a, &b);
+
+        let dx = Vec3::new(max.x() - min.x(), 0.0, 0.0);
+        let dy = Vec3::new(0.0, max.y() - min.y(), 0.0);
+        let dz = Vec3::new(0.0, 0.0, max.z() - min.z());
+
+        let mut sides = HittableList::new();
+        let mut side =
+            |q: Point3, u: Vec3, v: Vec3| sides.add(Quad::new(q, u, v, material.clone()));
+        side(Point3::new(min.x(), min.y(), max.z()), dx, dy); // 前
+        side(Point3::new(max.x(), min.y(), max.z()), -dz, dy); // 右
+        side(Point3::new(max.x(), min.y(), min.z()), -dx, dy); // 后
+        side(Point3::new(min.x(), min.y(), min.z()), dz, dy); // 左
+        side(Point3::new(min.x(), max.y(), max.z()), dx, -dz); // 上
+        side(Point3::new(min.x(), min.y(), min.z()), dx, dz); // 下
+
+        Self { sides }
+    }
+}
+
+impl Hittable for BoxShape {
+    fn hit(&self, r: &Ray, min: f64, max: f64) -> Option<Record<'_>> {
+        self.sides.hit(r, min, max)
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        self.sides.bounding_box()
+    }
+}
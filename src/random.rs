@@ -0,0 +1,39 @@
+use std::cell::RefCell;
+
+use rand::{rngs::SmallRng, Rng, SeedableRng};
+
+// 每个线程各自持有一个随机数生成器, 渲染时按像素重新设置种子,
+// 这样同一个种子无论串行还是并行渲染, 每个像素用到的随机数序列都一样
+thread_local! {
+    static RNG: RefCell<SmallRng> = RefCell::new(SmallRng::from_entropy());
+}
+
+// 用种子 seed 和流编号 stream 重置当前线程的随机数生成器
+pub fn reseed(seed: u64, stream: u64) {
+    let s = splitmix64(seed ^ splitmix64(stream));
+    RNG.with(|rng| *rng.borrow_mut() = SmallRng::seed_from_u64(s));
+}
+
+pub fn with_rng<F, R>(f: F) -> R
+where
+    F: FnOnce(&mut SmallRng) -> R,
+{
+    RNG.with(|rng| f(&mut rng.borrow_mut()))
+}
+
+// [0, 1) 之间的随机数
+pub fn random_double() -> f64 {
+    with_rng(|rng| rng.gen_range(0.0..1.0))
+}
+
+// [min, max) 之间的随机数
+pub fn random_range(min: f64, max: f64) -> f64 {
+    with_rng(|rng| rng.gen_range(min..max))
+}
+
+fn splitmix64(x: u64) -> u64 {
+    let mut z = x.wrapping_add(0x9e3779b97f4a7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+}
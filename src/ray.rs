@@ -3,11 +3,25 @@ use crate::vec3::Point3;
 pub struct Ray {
     pub origin: Point3,
     pub dir: Point3,
+    pub time: f64,               // 光线产生的时刻, 用于运动模糊
+    pub wavelength: Option<f64>, // 光谱渲染时光线的主波长 (nm), RGB 渲染时为 None
 }
 
 impl Ray {
-    pub fn new(origin: Point3, dir: Point3) -> Self {
-        Self { origin, dir }
+    pub fn new(origin: Point3, dir: Point3, time: f64) -> Self {
+        Self {
+            origin,
+            dir,
+            time,
+            wavelength: None,
+        }
+    }
+
+    pub fn with_wavelength(self, wavelength: f64) -> Self {
+        Self {
+            wavelength: Some(wavelength),
+            ..self
+        }
     }
 
     pub fn origin(&self) -> &Point3 {
@@ -18,6 +32,14 @@ impl Ray {
         &self.dir
     }
 
+    pub fn time(&self) -> f64 {
+        self.time
+    }
+
+    pub fn wavelength(&self) -> Option<f64> {
+        self.wavelength
+    }
+
     pub fn at(&self, t: f64) -> Point3 {
         self.origin + self.dir * t
     }
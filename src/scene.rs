@@ -0,0 +1,1060 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+
+use anyhow::{anyhow, Context, Result};
+use serde::Deserialize;
+use toml::Spanned;
+
+use crate::{
+    bvh::BvhNode,
+    camera::Camera,
+    hit::{Hittable, HittableList},
+    mat4::Mat4,
+    material::{
+        Coated, Conductor, Dielectric, DiffuseLight, Dispersion, HenyeyGreenstein, Isotropic,
+        Lambertian, Material, Metal, MixMaterial, Principled, RoughDielectric, ShadingNormal,
+        ThinFilm,
+    },
+    medium::{ConstantMedium, GridDensity, HeterogeneousMedium, NoiseDensity},
+    mesh::Mesh,
+    obj,
+    plane::Plane,
+    quad::{BoxShape, Quad},
+    ray::Ray,
+    sphere::{MovingSphere, Sphere},
+    texture::{CheckerTexture, ImageTexture, NoisePattern, NoiseTexture, SolidColor, Texture},
+    transform::Transform,
+    triangle::Triangle,
+    vec3::{Color, Point3, Vec3},
+};
+
+// 场景: 相机, 物体, 背景以及渲染参数
+pub struct Scene {
+    pub camera: CameraSettings,
+    pub world: Box<dyn Hittable>,
+    pub lights: HittableList<Arc<dyn Hittable>>, // 用于直接光照采样的光源, 同时也在 world 中
+    pub background: Background,
+    pub settings: Settings,
+}
+
+// 光线没有命中任何物体时的颜色
+#[derive(Clone, Copy, Debug)]
+pub enum Background {
+    Solid(Color),                           // 纯色, 黑色背景时场景只能被自身的光源照亮
+    Gradient { bottom: Color, top: Color }, // 按光线方向的 y 分量从下到上渐变
+}
+
+impl Background {
+    pub fn color(&self, r: &Ray) -> Color {
+        match self {
+            Self::Solid(c) => *c,
+            Self::Gradient { bottom, top } => {
+                let unit = r.direction().unit();
+                let t = 0.5 * (unit.y() + 1.0);
+                (1.0 - t) * *bottom + t * *top
+            }
+        }
+    }
+}
+
+// 默认是白色到浅蓝色的天空
+impl Default for Background {
+    fn default() -> Self {
+        Self::Gradient {
+            bottom: Color::new(1.0, 1.0, 1.0),
+            top: Color::new(0.5, 0.7, 1.0),
+        }
+    }
+}
+
+// 相机参数, 与 Camera::new 的参数一一对应
+#[derive(Clone, Copy, Debug)]
+pub struct CameraSettings {
+    pub lookfrom: Point3,
+    pub lookat: Point3,
+    pub vup: Vec3,
+    pub vfov: f64,
+    pub aspect_ratio: f64,
+    pub aperture: f64,
+    pub focus_dist: f64,
+    pub shutter: (f64, f64), // 快门打开和关闭的时刻
+}
+
+impl CameraSettings {
+    pub fn camera(&self) -> Camera {
+        Camera::new(
+            self.lookfrom,
+            self.lookat,
+            self.vup,
+            self.vfov,
+            self.aspect_ratio,
+            self.aperture,
+            self.focus_dist,
+        )
+        .with_shutter(self.shutter.0, self.shutter.1)
+    }
+}
+
+// 渲染参数
+#[derive(Clone, Copy, Debug)]
+pub struct Settings {
+    pub width: usize,
+    pub height: usize,
+    pub samples_per_pixel: usize, // 每个像素的采样数
+    pub min_depth: usize,         // 超过这个深度后用俄罗斯轮盘赌终止光线
+    pub max_depth: usize,         // 光线反射深度的上限
+    pub seed: u64,                // 随机数种子
+    pub spectral: bool,           // 光谱渲染, 用于色散等与波长有关的效果
+}
+
+impl Settings {
+    // 图像宽高的上限, 防止不合理的参数申请过多内存或者渲染不完
+    pub const MAX_SIZE: usize = 1 << 15;
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            width: 1200,
+            height: 800,
+            samples_per_pixel: 500,
+            min_depth: 5,
+            max_depth: 50,
+            seed: 0,
+            spectral: false,
+        }
+    }
+}
+
+impl Scene {
+    // 读取 TOML 格式的场景文件, 文件中引用的模型路径相对于场景文件所在目录
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let src = fs::read_to_string(path).with_context(|| format!("read {}", path.display()))?;
+        let dir = path.parent().unwrap_or(Path::new(""));
+        Self::parse(&src, dir).with_context(|| format!("parse {}", path.display()))
+    }
+
+    // 场景中的物体放进 BvhNode, 无限大的物体 (比如平面) 由 BvhNode 单独测试
+    pub fn world(objects: HittableList<Arc<dyn Hittable>>) -> Box<dyn Hittable> {
+        Box::new(BvhNode::new(objects))
+    }
+
+    pub fn parse(src: &str, dir: &Path) -> Result<Self> {
+        let desc: SceneDesc = toml::from_str(src)?;
+
+        // 报错时把字节偏移换算成行列号
+        let location = |offset: usize| {
+            let before = &src[..offset];
+            let line = before.matches('\n').count() + 1;
+            let column = before.len() - before.rfind('\n').map_or(0, |i| i + 1) + 1;
+            format!("line {}, column {}", line, column)
+        };
+
+        // 相机和渲染参数不合法时无法渲染, 在构建物体之前先检查
+        let camera_at = location(desc.camera.span().start);
+        let camera = desc.camera.into_inner();
+        camera.check(&camera_at)?;
+        let settings = match desc.render {
+            Some(render) => render
+                .get_ref()
+                .settings(&location(render.span().start), camera.aspect_ratio)?,
+            None => RenderDesc::default().settings(&camera_at, camera.aspect_ratio)?,
+        };
+
+        let emissive = desc
+            .materials
+            .iter()
+            .filter(|(_, m)| matches!(m.get_ref(), MaterialDesc::DiffuseLight { .. }))
+            .map(|(name, _)| name.clone())
+            .collect::<HashSet<_>>();
+        let materials = desc
+            .materials
+            .into_iter()
+            .map(|(name, m)| {
+                let at = location(m.span().start);
+                let material = m.into_inner().material(dir);
+                Ok((
+                    name.clone(),
+                    material.with_context(|| format!("{}: material '{}'", at, name))?,
+                ))
+            })
+            .collect::<Result<HashMap<_, _>>>()?;
+
+        // 先构建命名几何体, 它们只会被 instance 和 constant_medium 引用, 本身不会直接放进场景
+        let mut geometry = HashMap::new();
+        for (name, object) in desc.geometry {
+            let at = location(object.span().start);
+            let objects = object.into_inner().build(dir, &at, &materials, None)?;
+            let shared: Arc<dyn Hittable> = match objects.len() {
+                1 => objects.into_iter().next().unwrap(),
+                _ => Arc::new(BvhNode::new(objects.into_iter().collect())),
+            };
+            geometry.insert(name, shared);
+        }
+
+        let mut world: HittableList<Arc<dyn Hittable>> = HittableList::new();
+        let mut lights: HittableList<Arc<dyn Hittable>> = HittableList::new();
+        for object in desc.objects {
+            let at = location(object.span().start);
+            let object = object.into_inner();
+            let is_light = object.light().is_some_and(|m| emissive.contains(m));
+            let objects = object.build(dir, &at, &materials, Some(&geometry))?;
+            for object in objects {
+                if is_light {
+                    lights.add(object.clone());
+                }
+                world.add(object);
+            }
+        }
+
+        Ok(Self {
+            camera: camera.settings(),
+            world: Self::world(world),
+            lights,
+            background: desc
+                .background
+                .map_or_else(Background::default, |bg| bg.background()),
+            settings,
+        })
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SceneDesc {
+    render: Option<Spanned<RenderDesc>>,
+    camera: Spanned<CameraDesc>,
+    background: Option<BackgroundDesc>,
+    #[serde(default)]
+    materials: HashMap<String, Spanned<MaterialDesc>>,
+    #[serde(default)]
+    geometry: HashMap<String, Spanned<ObjectDesc>>,
+    #[serde(default)]
+    objects: Vec<Spanned<ObjectDesc>>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields, default)]
+struct RenderDesc {
+    width: usize,
+    samples_per_pixel: usize,
+    min_depth: usize,
+    max_depth: usize,
+    seed: u64,
+    spectral: bool,
+}
+
+impl RenderDesc {
+    fn settings(&self, at: &str, aspect_ratio: f64) -> Result<Settings> {
+        let width = self.width;
+        let height = (width as f64 / aspect_ratio) as usize;
+        if !(2..=Settings::MAX_SIZE).contains(&width) || !(2..=Settings::MAX_SIZE).contains(&height)
+        {
+            return Err(anyhow!(
+                "{}: image size {}x{} is out of range, width and height must be in [2, {}]",
+                at,
+                width,
+                height,
+                Settings::MAX_SIZE
+            ));
+        }
+        if self.samples_per_pixel == 0 {
+            return Err(anyhow!("{}: samples_per_pixel must be at least 1", at));
+        }
+
+        Ok(Settings {
+            width,
+            height,
+            samples_per_pixel: self.samples_per_pixel,
+            min_depth: self.min_depth,
+            max_depth: self.max_depth,
+            seed: self.seed,
+            spectral: self.spectral,
+        })
+    }
+}
+
+impl Default for RenderDesc {
+    fn default() -> Self {
+        let settings = Settings::default();
+        Self {
+            width: settings.width,
+            samples_per_pixel: settings.samples_per_pixel,
+            min_depth: settings.min_depth,
+            max_depth: settings.max_depth,
+            seed: settings.seed,
+            spectral: settings.spectral,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CameraDesc {
+    lookfrom: [f64; 3],
+    lookat: [f64; 3],
+    #[serde(default = "CameraDesc::default_vup")]
+    vup: [f64; 3],
+    vfov: f64,
+    #[serde(default = "CameraDesc::default_aspect_ratio")]
+    aspect_ratio: f64,
+    #[serde(default)]
+    aperture: f64,
+    focus_dist: Option<f64>, // 缺省时对焦到观察点
+    #[serde(default)]
+    shutter: [f64; 2],
+}
+
+impl CameraDesc {
+    fn default_vup() -> [f64; 3] {
+        [0.0, 1.0, 0.0]
+    }
+
+    fn default_aspect_ratio() -> f64 {
+        3.0 / 2.0
+    }
+
+    fn check(&self, at: &str) -> Result<()> {
+        if !(self.aspect_ratio.is_finite() && self.aspect_ratio > 0.0) {
+            return Err(anyhow!("{}: aspect_ratio must be a positive number", at));
+        }
+        if !(self.vfov > 0.0 && self.vfov < 180.0) {
+            return Err(anyhow!("{}: vfov must be in (0, 180) degrees", at));
+        }
+        Ok(())
+    }
+
+    fn settings(&self) -> CameraSettings {
+        let lookfrom = Point3::from(self.lookfrom);
+        let lookat = Point3::from(self.lookat);
+        let focus_dist = self
+            .focus_dist
+            .unwrap_or_else(|| (lookfrom - lookat).length());
+
+        CameraSettings {
+            lookfrom,
+            lookat,
+            vup: self.vup.into(),
+            vfov: self.vfov,
+            aspect_ratio: self.aspect_ratio,
+            aperture: self.aperture,
+            focus_dist,
+            shutter: (self.shutter[0], self.shutter[1]),
+        }
+    }
+}
+
+// background = [r, g, b] 或者 background = { bottom = [r, g, b], top = [r, g, b] }
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum BackgroundDesc {
+    Solid([f64; 3]),
+    Gradient { bottom: [f64; 3], top: [f64; 3] },
+}
+
+impl BackgroundDesc {
+    fn background(self) -> Background {
+        match self {
+            Self::Solid(c) => Background::Solid(c.into()),
+            Self::Gradient { bottom, top } => Background::Gradient {
+                bottom: bottom.into(),
+                top: top.into(),
+            },
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum MaterialDesc {
+    Lambertian {
+        albedo: TextureDesc,
+    },
+    Metal {
+        albedo: TextureDesc,
+        fuzz: f64,
+    },
+    // 折射率 ir 和色散系数 cauchy, sellmeier 三者选一个
+    Dielectric {
+        ir: Option<f64>,
+        cauchy: Option<[f64; 2]>,
+        sellmeier: Option<SellmeierDesc>,
+        #[serde(default)]
+        absorption: [f64; 3], // 每单位距离的吸收系数, 吸收越多的颜色透过得越少
+    },
+    // GGX 微表面材质, roughness 在 [0, 1] 之间, 为 0 时是光滑的镜面
+    Conductor {
+        albedo: TextureDesc,
+        roughness: f64,
+    },
+    RoughDielectric {
+        ir: f64,
+        roughness: f64,
+    },
+    // 薄膜的厚度 (nm) 和折射率, base 是薄膜下面的电介质或金属
+    ThinFilm {
+        thickness: f64,
+        ior: f64,
+        base: FilmBaseDesc,
+    },
+    // 参数较多, 除 base_color 外都可以省略
+    Principled(PrincipledDesc),
+    // 按 weight 在 a 和 b 之间混合, weight 是选择 b 的概率
+    Mix {
+        a: Box<MaterialDesc>,
+        b: Box<MaterialDesc>,
+        weight: WeightDesc,
+    },
+    // 在 base 外面涂一层折射率为 ir 的透明涂层
+    Coated {
+        base: Box<MaterialDesc>,
+        #[serde(default = "MaterialDesc::default_ir")]
+        ir: f64,
+    },
+    // 用切线空间的法线贴图扰动 base 的法线
+    NormalMap {
+        base: Box<MaterialDesc>,
+        file: String,
+        #[serde(default = "MaterialDesc::default_strength")]
+        strength: f64,
+    },
+    // 用高度纹理扰动 base 的法线, 高度是纹理值乘以 scale
+    Bump {
+        base: Box<MaterialDesc>,
+        height: TextureDesc,
+        scale: f64,
+    },
+    DiffuseLight {
+        emit: [f64; 3],
+    },
+    Isotropic {
+        albedo: TextureDesc,
+    },
+    HenyeyGreenstein {
+        albedo: TextureDesc,
+        g: f64,
+    },
+}
+
+impl MaterialDesc {
+    fn default_ir() -> f64 {
+        1.5
+    }
+
+    fn default_strength() -> f64 {
+        1.0
+    }
+
+    fn material(self, dir: &Path) -> Result<Arc<dyn Material>> {
+        Ok(match self {
+            Self::Lambertian { albedo } => Arc::new(Lambertian::with_texture(albedo.texture(dir)?)),
+            Self::Metal { albedo, fuzz } => {
+                Arc::new(Metal::with_texture(albedo.texture(dir)?, fuzz))
+            }
+            Self::Dielectric {
+                ir,
+                cauchy,
+                sellmeier,
+                absorption,
+            } => {
+                let absorption = absorption.into();
+                match (ir, cauchy, sellmeier) {
+                    (Some(ir), None, None) => Arc::new(Dielectric::with_absorption(ir, absorption)),
+                    (None, Some([a, b]), None) => Arc::new(Dielectric::with_dispersion(
+                        Dispersion::Cauchy { a, b },
+                        absorption,
+                    )),
+                    (None, None, Some(SellmeierDesc { b, c })) => Arc::new(
+                        Dielectric::with_dispersion(Dispersion::Sellmeier { b, c }, absorption),
+                    ),
+                    _ => return Err(anyhow!("expect exactly one of ir, cauchy and sellmeier")),
+                }
+            }
+            Self::Conductor { albedo, roughness } => {
+                Arc::new(Conductor::with_texture(albedo.texture(dir)?, roughness))
+            }
+            Self::RoughDielectric { ir, roughness } => {
+                Arc::new(RoughDielectric::new(ir, roughness))
+            }
+            Self::ThinFilm {
+                thickness,
+                ior,
+                base,
+            } => Arc::new(match base {
+                FilmBaseDesc::Dielectric { ir } => ThinFilm::dielectric(ir, thickness, ior),
+                FilmBaseDesc::Metal { albedo, fuzz } => {
+                    ThinFilm::metal(albedo.into(), fuzz, thickness, ior)
+                }
+            }),
+            Self::Principled(desc) => Arc::new(desc.material(dir)?),
+            Self::Mix { a, b, weight } => {
+                let (a, b) = (a.material(dir)?, b.material(dir)?);
+                Arc::new(match weight {
+                    WeightDesc::Constant(w) => MixMaterial::new(a, b, w),
+                    WeightDesc::Texture(t) => MixMaterial::with_texture(a, b, t.texture(dir)?),
+                })
+            }
+            Self::Coated { base, ir } => Arc::new(Coated::new(base.material(dir)?, ir)),
+            Self::NormalMap {
+                base,
+                file,
+                strength,
+            } => {
+                let map = Arc::new(ImageTexture::open_linear(dir.join(file))?);
+                Arc::new(ShadingNormal::normal_map(
+                    base.material(dir)?,
+                    map,
+                    strength,
+                ))
+            }
+            Self::Bump {
+                base,
+                height,
+                scale,
+            } => Arc::new(ShadingNormal::bump(
+                base.material(dir)?,
+                height.texture(dir)?,
+                scale,
+            )),
+            Self::DiffuseLight { emit } => Arc::new(DiffuseLight::new(emit.into())),
+            Self::Isotropic { albedo } => Arc::new(Isotropic::with_texture(albedo.texture(dir)?)),
+            Self::HenyeyGreenstein { albedo, g } => {
+                Arc::new(HenyeyGreenstein::with_texture(albedo.texture(dir)?, g))
+            }
+        })
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum FilmBaseDesc {
+    Dielectric {
+        ir: f64,
+    },
+    Metal {
+        albedo: [f64; 3],
+        #[serde(default)]
+        fuzz: f64,
+    },
+}
+
+// Sellmeier 方程的系数, c 的单位是 μm²
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SellmeierDesc {
+    b: [f64; 3],
+    c: [f64; 3],
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PrincipledDesc {
+    base_color: TextureDesc,
+    #[serde(default)]
+    metallic: f64,
+    #[serde(default = "PrincipledDesc::default_half")]
+    roughness: f64,
+    #[serde(default = "PrincipledDesc::default_half")]
+    specular: f64,
+    #[serde(default)]
+    specular_tint: f64,
+    #[serde(default)]
+    sheen: f64,
+    #[serde(default = "PrincipledDesc::default_half")]
+    sheen_tint: f64,
+    #[serde(default)]
+    clearcoat: f64,
+    #[serde(default = "PrincipledDesc::default_one")]
+    clearcoat_gloss: f64,
+    #[serde(default)]
+    transmission: f64,
+    #[serde(default = "PrincipledDesc::default_ior")]
+    ior: f64,
+}
+
+impl PrincipledDesc {
+    fn default_half() -> f64 {
+        0.5
+    }
+
+    fn default_one() -> f64 {
+        1.0
+    }
+
+    fn default_ior() -> f64 {
+        1.5
+    }
+
+    fn material(self, dir: &Path) -> Result<Principled> {
+        Ok(Principled {
+            metallic: self.metallic,
+            roughness: self.roughness,
+            specular: self.specular,
+            specular_tint: self.specular_tint,
+            sheen: self.sheen,
+            sheen_tint: self.sheen_tint,
+            clearcoat: self.clearcoat,
+            clearcoat_gloss: self.clearcoat_gloss,
+            transmission: self.transmission,
+            ior: self.ior,
+            ..Principled::with_texture(self.base_color.texture(dir)?)
+        })
+    }
+}
+
+// 混合权重可以是常数, 也可以是纹理
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum WeightDesc {
+    Constant(f64),
+    Texture(TextureDesc),
+}
+
+// 纹理可以直接写颜色 [r, g, b], 也可以写成带 type 的表
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum TextureDesc {
+    Solid([f64; 3]),
+    Texture(TextureKind),
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum TextureKind {
+    Checker {
+        scale: f64,
+        even: Box<TextureDesc>,
+        odd: Box<TextureDesc>,
+    },
+    Image {
+        file: String,
+    },
+    Noise {
+        #[serde(default = "TextureKind::default_pattern")]
+        pattern: NoisePatternDesc,
+        #[serde(default = "TextureKind::default_scale")]
+        scale: f64,
+        #[serde(default)]
+        seed: u64,
+        colors: Option<[[f64; 3]; 2]>, // 缺省时按图案选择颜色
+    },
+}
+
+impl TextureKind {
+    fn default_pattern() -> NoisePatternDesc {
+        NoisePatternDesc::Smooth
+    }
+
+    fn default_scale() -> f64 {
+        1.0
+    }
+}
+
+#[derive(Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum NoisePatternDesc {
+    Smooth,
+    Turbulence,
+    Marble,
+    Wood,
+}
+
+impl From<NoisePatternDesc> for NoisePattern {
+    fn from(pattern: NoisePatternDesc) -> Self {
+        match pattern {
+            NoisePatternDesc::Smooth => Self::Smooth,
+            NoisePatternDesc::Turbulence => Self::Turbulence,
+            NoisePatternDesc::Marble => Self::Marble,
+            NoisePatternDesc::Wood => Self::Wood,
+        }
+    }
+}
+
+impl TextureDesc {
+    fn texture(self, dir: &Path) -> Result<Arc<dyn Texture>> {
+        Ok(match self {
+            Self::Solid(c) => Arc::new(SolidColor::new(c.into())),
+            Self::Texture(TextureKind::Checker { scale, even, odd }) => Arc::new(
+                CheckerTexture::new(scale, even.texture(dir)?, odd.texture(dir)?),
+            ),
+            Self::Texture(TextureKind::Image { file }) => {
+                Arc::new(ImageTexture::open(dir.join(file))?)
+            }
+            Self::Texture(TextureKind::Noise {
+                pattern,
+                scale,
+                seed,
+                colors,
+            }) => Arc::new(match (pattern.into(), colors) {
+                (pattern, Some([a, b])) => {
+                    NoiseTexture::new(seed, pattern, scale, a.into(), b.into())
+                }
+                (NoisePattern::Marble, None) => NoiseTexture::marble(seed, scale),
+                (NoisePattern::Wood, None) => NoiseTexture::wood(seed, scale),
+                (pattern, None) => {
+                    let (black, white) = (Color::new(0.0, 0.0, 0.0), Color::new(1.0, 1.0, 1.0));
+                    NoiseTexture::new(seed, pattern, scale, black, white)
+                }
+            }),
+        })
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum ObjectDesc {
+    Sphere {
+        center: [f64; 3],
+        radius: f64,
+        material: String,
+    },
+    MovingSphere {
+        center0: [f64; 3],
+        center1: [f64; 3],
+        #[serde(default)]
+        time0: f64,
+        #[serde(default = "ObjectDesc::default_time1")]
+        time1: f64,
+        radius: f64,
+        material: String,
+    },
+    Triangle {
+        vertices: [[f64; 3]; 3],
+        material: String,
+    },
+    Quad {
+        q: [f64; 3],
+        u: [f64; 3],
+        v: [f64; 3],
+        material: String,
+    },
+    Box {
+        a: [f64; 3],
+        b: [f64; 3],
+        material: String,
+    },
+    Plane {
+        point: [f64; 3],
+        normal: [f64; 3],
+        material: String,
+    },
+    Mesh {
+        file: String,
+    },
+    // 引用 [geometry] 中的命名几何体, 同一份几何体可以被多个实例共享
+    Instance {
+        geometry: String,
+        #[serde(default)]
+        translate: [f64; 3],
+        #[serde(default)]
+        rotate: [f64; 3], // 绕 x, y, z 轴旋转的角度
+        #[serde(default)]
+        scale: ScaleDesc,
+    },
+    // 以 [geometry] 中的命名几何体为边界的均匀介质, 材质一般是 isotropic
+    ConstantMedium {
+        boundary: String,
+        density: f64,
+        material: String,
+    },
+    // 密度不均匀的介质, 材质一般是 isotropic 或 henyey_greenstein
+    HeterogeneousMedium {
+        boundary: String,
+        density: DensityDesc,
+        material: String,
+    },
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum DensityDesc {
+    // Perlin 湍流, 取值在 [0, density] 之间
+    Noise {
+        density: f64,
+        #[serde(default = "DensityDesc::default_scale")]
+        scale: f64,
+        #[serde(default)]
+        seed: u64,
+    },
+    // 原始体素文件, size[0] * size[1] * size[2] 个小端序 f32, 网格铺满边界的包围盒
+    Grid {
+        file: String,
+        size: [usize; 3],
+        #[serde(default = "DensityDesc::default_scale")]
+        scale: f64,
+    },
+}
+
+impl DensityDesc {
+    fn default_scale() -> f64 {
+        1.0
+    }
+}
+
+impl ObjectDesc {
+    fn default_time1() -> f64 {
+        1.0
+    }
+
+    // 支持光源采样的物体所用的材质
+    fn light(&self) -> Option<&str> {
+        match self {
+            Self::Sphere { material, .. } | Self::Quad { material, .. } => Some(material),
+            _ => None,
+        }
+    }
+
+    // geometry 为 None 时不允许引用其他几何体, 避免几何体之间相互引用
+    fn build(
+        self,
+        dir: &Path,
+        at: &str,
+        materials: &HashMap<String, Arc<dyn Material>>,
+        geometry: Option<&HashMap<String, Arc<dyn Hittable>>>,
+    ) -> Result<Vec<Arc<dyn Hittable>>> {
+        let material = |name: &str| {
+            let m = materials.get(name).cloned();
+            m.ok_or_else(|| anyhow!("{}: unknown material '{}'", at, name))
+        };
+
+        let object: Arc<dyn Hittable> = match self {
+            Self::Sphere {
+                center,
+                radius,
+                material: m,
+            } => Arc::new(Sphere::new(center.into(), radius, material(&m)?)),
+            Self::MovingSphere {
+                center0,
+                center1,
+                time0,
+                time1,
+                radius,
+                material: m,
+            } => {
+                let (c0, c1) = (center0.into(), center1.into());
+                let m = material(&m)?;
+                Arc::new(MovingSphere::new(c0, c1, time0, time1, radius, m))
+            }
+            Self::Triangle {
+                vertices: [v0, v1, v2],
+                material: m,
+            } => Arc::new(Triangle::new(
+                v0.into(),
+                v1.into(),
+                v2.into(),
+                material(&m)?,
+            )),
+            Self::Quad {
+                q,
+                u,
+                v,
+                material: m,
+            } => Arc::new(Quad::new(q.into(), u.into(), v.into(), material(&m)?)),
+            Self::Box { a, b, material: m } => {
+                Arc::new(BoxShape::new(a.into(), b.into(), material(&m)?))
+            }
+            Self::Plane {
+                point,
+                normal,
+                material: m,
+            } => Arc::new(Plane::new(point.into(), normal.into(), material(&m)?)),
+            Self::Mesh { file } => {
+                let mesh = obj::load(dir.join(&file)).with_context(|| at.to_string())?;
+                return Ok(Mesh::triangles(&Arc::new(mesh))
+                    .map(|t| Arc::new(t) as Arc<dyn Hittable>)
+                    .collect());
+            }
+            Self::Instance {
+                geometry: name,
+                translate,
+                rotate: [rx, ry, rz],
+                scale,
+            } => {
+                let geometry =
+                    geometry.ok_or_else(|| anyhow!("{}: geometry can not be an instance", at))?;
+                let object = geometry
+                    .get(&name)
+                    .ok_or_else(|| anyhow!("{}: unknown geometry '{}'", at, name))?;
+
+                // 先缩放, 再依次绕 x, y, z 轴旋转, 最后平移
+                let matrix = Mat4::translate(translate.into())
+                    * Mat4::rotate_z(rz)
+                    * Mat4::rotate_y(ry)
+                    * Mat4::rotate_x(rx)
+                    * Mat4::scale(scale.vec3());
+                let transform = Transform::new(object.clone(), matrix)
+                    .map_err(|_| anyhow!("{}: instance transform is not invertible", at))?;
+                Arc::new(transform)
+            }
+            Self::ConstantMedium {
+                boundary,
+                density,
+                material: m,
+            } => {
+                let geometry =
+                    geometry.ok_or_else(|| anyhow!("{}: geometry can not be a medium", at))?;
+                let boundary = geometry
+                    .get(&boundary)
+                    .ok_or_else(|| anyhow!("{}: unknown geometry '{}'", at, boundary))?;
+                let m = material(&m)?;
+                Arc::new(ConstantMedium::with_phase(boundary.clone(), density, m))
+            }
+            Self::HeterogeneousMedium {
+                boundary,
+                density,
+                material: m,
+            } => {
+                let geometry =
+                    geometry.ok_or_else(|| anyhow!("{}: geometry can not be a medium", at))?;
+                let boundary = geometry
+                    .get(&boundary)
+                    .ok_or_else(|| anyhow!("{}: unknown geometry '{}'", at, boundary))?
+                    .clone();
+                let m = material(&m)?;
+                match density {
+                    DensityDesc::Noise {
+                        density,
+                        scale,
+                        seed,
+                    } => {
+                        let density = NoiseDensity::new(seed, scale, density);
+                        Arc::new(HeterogeneousMedium::new(boundary, density, m))
+                    }
+                    DensityDesc::Grid { file, size, scale } => {
+                        let bounds = boundary
+                            .bounding_box()
+                            .ok_or_else(|| anyhow!("{}: grid boundary must be bounded", at))?;
+                        let density = GridDensity::open(dir.join(&file), size, bounds, scale)
+                            .with_context(|| at.to_string())?;
+                        Arc::new(HeterogeneousMedium::new(boundary, density, m))
+                    }
+                }
+            }
+        };
+
+        Ok(vec![object])
+    }
+}
+
+// 缩放可以是统一的一个数, 也可以每个轴分别指定
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ScaleDesc {
+    Uniform(f64),
+    Axes([f64; 3]),
+}
+
+impl ScaleDesc {
+    fn vec3(&self) -> Vec3 {
+        match *self {
+            Self::Uniform(s) => Vec3::new(s, s, s),
+            Self::Axes(s) => s.into(),
+        }
+    }
+}
+
+impl Default for ScaleDesc {
+    fn default() -> Self {
+        Self::Uniform(1.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CAMERA: &str =
+        "[camera]\nlookfrom = [0.0, 0.0, 5.0]\nlookat = [0.0, 0.0, 0.0]\nvfov = 40.0\n";
+
+    // 返回包含所有原因的完整错误信息
+    fn parse_err(src: &str) -> String {
+        match Scene::parse(src, Path::new("")) {
+            Ok(_) => panic!("expect an error"),
+            Err(err) => format!("{:#}", err),
+        }
+    }
+
+    #[test]
+    fn parse() {
+        let src = format!(
+            "{}\n[render]\nwidth = 300\n\n[materials.m]\ntype = \"lambertian\"\nalbedo = [0.5, 0.5, 0.5]\n\n\
+             [[objects]]\ntype = \"sphere\"\ncenter = [0.0, 0.0, 0.0]\nradius = 1.0\nmaterial = \"m\"\n",
+            CAMERA
+        );
+        let scene = Scene::parse(&src, Path::new("")).unwrap();
+        assert_eq!(scene.settings.width, 300);
+        assert_eq!(scene.settings.height, 200);
+        assert!(scene.world.bounding_box().is_some());
+    }
+
+    #[test]
+    fn object_error_location() {
+        let src = format!(
+            "{}\n[[objects]]\ntype = \"sphere\"\ncenter = [0.0, 0.0, 0.0]\nradius = 1.0\nmaterial = \"missing\"\n",
+            CAMERA
+        );
+        let err = parse_err(&src);
+        assert!(
+            err.contains("line 6, column 1: unknown material 'missing'"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn material_error_location() {
+        let src = format!(
+            "{}\n[materials.m]\ntype = \"dielectric\"\nir = 1.5\ncauchy = [1.5, 4000.0]\n",
+            CAMERA
+        );
+        let err = parse_err(&src);
+        assert!(err.contains("line 6, column 1: material 'm'"), "{}", err);
+        assert!(
+            err.contains("expect exactly one of ir, cauchy and sellmeier"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn invalid_camera() {
+        for (fields, message) in [
+            (
+                "vfov = 40.0\naspect_ratio = 0.0",
+                "aspect_ratio must be a positive number",
+            ),
+            (
+                "vfov = 40.0\naspect_ratio = -1.0",
+                "aspect_ratio must be a positive number",
+            ),
+            ("vfov = 180.0", "vfov must be in (0, 180) degrees"),
+            ("vfov = 0.0", "vfov must be in (0, 180) degrees"),
+        ] {
+            let src = format!(
+                "[camera]\nlookfrom = [0.0, 0.0, 5.0]\nlookat = [0.0, 0.0, 0.0]\n{fields}\n"
+            );
+            let err = parse_err(&src);
+            assert!(
+                err.contains(&format!("line 1, column 1: {}", message)),
+                "{}",
+                err
+            );
+        }
+    }
+
+    #[test]
+    fn invalid_render() {
+        for render in ["width = 0", "width = 1000000", "samples_per_pixel = 0"] {
+            let src = format!("{}\n[render]\n{}\n", CAMERA, render);
+            let err = parse_err(&src);
+            assert!(err.contains("line 6, column 1: "), "{}", err);
+        }
+
+        // 长宽比太小时推算出的高度超出范围
+        let err = parse_err(&format!("{}aspect_ratio = 1e-9\n", CAMERA));
+        assert!(err.contains("line 1, column 1: image size"), "{}", err);
+    }
+}
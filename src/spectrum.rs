@@ -0,0 +1,112 @@
+use crate::{random, vec3::Color};
+
+// 光谱渲染的波长范围 (nm)
+pub const LAMBDA_MIN: f64 = 360.0;
+pub const LAMBDA_MAX: f64 = 830.0;
+
+// 一条路径携带的一组波长 (hero wavelength sampling)
+// 主波长随机采样, 另外两个波长在可见光范围内等间隔平移得到, 三个波长的值刚好放进一个 Color
+#[derive(Clone, Copy, Debug)]
+pub struct Wavelengths {
+    lambda: [f64; 3],
+    pdf: [f64; 3], // 为 0 表示这个波长已经被终止
+}
+
+impl Wavelengths {
+    pub fn sample() -> Self {
+        let u = random::random_double();
+        let mut lambda = [0.0; 3];
+        let mut pdf = [0.0; 3];
+        for i in 0..3 {
+            let u = (u + i as f64 / 3.0).fract();
+            lambda[i] = sample_visible(u);
+            pdf[i] = visible_pdf(lambda[i]);
+        }
+        Self { lambda, pdf }
+    }
+
+    // 主波长, 折射率随波长变化的材质只按它计算
+    pub fn hero(&self) -> f64 {
+        self.lambda[0]
+    }
+
+    // 只保留主波长, 用于色散之后各个波长的路径不再相同的情况
+    pub fn terminate_secondary(&mut self) {
+        if self.pdf[1] == 0.0 {
+            return;
+        }
+        self.pdf = [self.pdf[0] / 3.0, 0.0, 0.0];
+    }
+
+    // RGB 颜色 (反射率, 发光强度等) 在这组波长上的值
+    pub fn upsample(&self, rgb: &Color) -> Color {
+        let [a, b, c] = self.lambda.map(|l| upsample(rgb, l));
+        Color::new(a, b, c)
+    }
+
+    // 把这组波长上的辐射亮度转换到线性 sRGB
+    pub fn to_rgb(&self, values: &Color) -> Color {
+        let mut xyz = Color::default();
+        for i in 0..3 {
+            if self.pdf[i] > 0.0 {
+                xyz += values[i] / self.pdf[i] * cie_xyz(self.lambda[i]);
+            }
+        }
+        xyz /= 3.0;
+
+        // 值恒为 1 的光谱对应的 RGB, 用来把等能白光归一化为 (1, 1, 1)
+        let white = Color::new(128.335, 101.544, 97.117);
+        let rgb = xyz_to_srgb(&xyz);
+        Color::new(
+            rgb.x() / white.x(),
+            rgb.y() / white.y(),
+            rgb.z() / white.z(),
+        )
+    }
+}
+
+// 按人眼的灵敏度在可见光范围内采样波长, 见 pbrt-v4 的 SampleVisibleWavelengths
+fn sample_visible(u: f64) -> f64 {
+    538.0 - 138.888889 * (0.85691062 - 1.82750197 * u).atanh()
+}
+
+fn visible_pdf(lambda: f64) -> f64 {
+    if !(LAMBDA_MIN..=LAMBDA_MAX).contains(&lambda) {
+        return 0.0;
+    }
+    0.0039398042 / (0.0072 * (lambda - 538.0)).cosh().powi(2)
+}
+
+// CIE 1931 标准观察者的颜色匹配函数, 用分段高斯函数拟合 (Wyman et al. 2013)
+pub fn cie_xyz(lambda: f64) -> Color {
+    let g = |mu: f64, s1: f64, s2: f64| {
+        let t = (lambda - mu) / if lambda < mu { s1 } else { s2 };
+        (-0.5 * t * t).exp()
+    };
+
+    Color::new(
+        1.056 * g(599.8, 37.9, 31.0) + 0.362 * g(442.0, 16.0, 26.7) - 0.065 * g(501.1, 20.4, 26.2),
+        0.821 * g(568.8, 46.9, 40.5) + 0.286 * g(530.9, 16.3, 31.1),
+        1.217 * g(437.0, 11.8, 36.0) + 0.681 * g(459.0, 26.0, 13.8),
+    )
+}
+
+// XYZ 到线性 sRGB (D65 白点)
+pub fn xyz_to_srgb(xyz: &Color) -> Color {
+    let (x, y, z) = (xyz.x(), xyz.y(), xyz.z());
+    Color::new(
+        3.2404542 * x - 1.5371385 * y - 0.4985314 * z,
+        -0.9692660 * x + 1.8760108 * y + 0.0415560 * z,
+        0.0556434 * x - 0.2040259 * y + 1.0572252 * z,
+    )
+}
+
+// RGB 到光谱的上采样: 蓝, 绿, 红三个平滑的波段加起来处处为 1,
+// 所以白色对应值恒为 1 的光谱, 并且反射率在 [0, 1] 之间时光谱也在 [0, 1] 之间
+pub fn upsample(rgb: &Color, lambda: f64) -> f64 {
+    let step = |edge: f64| 1.0 / (1.0 + (-(lambda - edge) / 8.0).exp());
+    let red = step(588.0);
+    let blue = 1.0 - step(488.0);
+    let green = 1.0 - red - blue;
+    rgb.x() * red + rgb.y() * green + rgb.z() * blue
+}
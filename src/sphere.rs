@@ -1,15 +1,19 @@
-use std::rc::Rc;
+use std::f64::consts::PI;
+use std::sync::Arc;
 
-use crate::{hit::Hittable, hit::Record, material::Material, vec3::Point3, vec3::Vec3};
+use crate::{
+    aabb::Aabb, hit::Hittable, hit::Record, material::Material, random, ray::Ray, vec3::Onb,
+    vec3::Point3, vec3::Vec3,
+};
 
 pub struct Sphere {
-    pub center: Point3,             // 中心点
-    pub radius: f64,                // 半径
-    pub material: Rc<dyn Material>, // 材质
+    pub center: Point3,              // 中心点
+    pub radius: f64,                 // 半径
+    pub material: Arc<dyn Material>, // 材质
 }
 
 impl Sphere {
-    pub fn new(center: Point3, radius: f64, material: Rc<dyn Material>) -> Self {
+    pub fn new(center: Point3, radius: f64, material: Arc<dyn Material>) -> Self {
         Self {
             center,
             radius,
@@ -18,43 +22,171 @@ impl Sphere {
     }
 }
 
-unsafe impl Sync for Sphere {}
+impl Sphere {
+    // 单位球面上的点 p 对应的纹理坐标
+    // u: 从 -x 轴开始绕 y 轴的角度 [0, 2π] 映射到 [0, 1]
+    // v: 从 -y 到 +y 的角度 [0, π] 映射到 [0, 1]
+    fn uv(p: &Point3) -> (f64, f64) {
+        let theta = (-p.y()).acos();
+        let phi = (-p.z()).atan2(p.x()) + PI;
+        (phi / (2.0 * PI), theta / PI)
+    }
 
-impl Hittable for Sphere {
-    fn hit(&self, r: &crate::ray::Ray, min: f64, max: f64) -> Option<Record> {
-        let oc = r.origin() - &self.center;
-
-        let a = r.direction().length_square();
-        let half_b = Vec3::dot(&oc, r.direction());
-        let c = oc.length_square() - self.radius * self.radius;
-        let discriminant = half_b * half_b - a * c;
-        if discriminant < 0.0 {
+    // 单位法线 n 处的 ∂p/∂u 和 ∂p/∂v, 两极处 u 没有定义时返回 None
+    fn tangents(n: &Vec3, radius: f64) -> Option<(Vec3, Vec3)> {
+        let sin_theta = (n.x() * n.x() + n.z() * n.z()).sqrt();
+        if sin_theta < 1e-8 {
             return None;
         }
 
-        let sqrtd = discriminant.sqrt();
-        let mut root = (-half_b - sqrtd) / a;
+        let (x, y, z) = (n.x(), n.y(), n.z());
+        let dpdu = 2.0 * PI * radius * Vec3::new(z, 0.0, -x);
+        let dpdv = PI * radius * Vec3::new(-x * y, sin_theta * sin_theta, -y * z) / sin_theta;
+        Some((dpdu, dpdv))
+    }
+}
+
+impl Hittable for Sphere {
+    fn hit(&self, r: &Ray, min: f64, max: f64) -> Option<Record<'_>> {
+        hit_sphere(
+            &self.center,
+            self.radius,
+            self.material.as_ref(),
+            r,
+            min,
+            max,
+        )
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let r = Vec3::new(self.radius, self.radius, self.radius);
+        Some(Aabb::new(self.center - r, self.center + r))
+    }
+
+    // 在球对 origin 张成的圆锥内均匀采样, 圆锥的立体角是 2π(1 - cosθmax)
+    fn pdf_value(&self, origin: &Point3, dir: &Vec3) -> f64 {
+        let r = Ray::new(*origin, *dir, 0.0);
+        if self.hit(&r, 0.001, f64::INFINITY).is_none() {
+            return 0.0;
+        }
+
+        let distance_squared = (self.center - *origin).length_square();
+        let radius_squared = self.radius * self.radius;
+        if distance_squared <= radius_squared {
+            // origin 在球内部, 各个方向都能看到球面
+            return 1.0 / (4.0 * PI);
+        }
+
+        let cos_theta_max = (1.0 - radius_squared / distance_squared).sqrt();
+        1.0 / (2.0 * PI * (1.0 - cos_theta_max))
+    }
+
+    fn random(&self, origin: &Point3) -> Vec3 {
+        let direction = self.center - *origin;
+        let distance_squared = direction.length_square();
+        let radius_squared = self.radius * self.radius;
+        if distance_squared <= radius_squared {
+            return Vec3::random_unit_vector();
+        }
+
+        // 在以 direction 为轴的圆锥内采样, 再转换到世界坐标
+        let cos_theta_max = (1.0 - radius_squared / distance_squared).sqrt();
+        let z = 1.0 + random::random_double() * (cos_theta_max - 1.0);
+        let phi = 2.0 * PI * random::random_double();
+        let sin_theta = (1.0 - z * z).sqrt();
+
+        let local = Vec3::new(sin_theta * phi.cos(), sin_theta * phi.sin(), z);
+        Onb::new(&direction).local(&local)
+    }
+}
+
+// 运动的球, 球心在 time0 到 time1 之间从 center0 匀速移动到 center1
+pub struct MovingSphere {
+    pub center0: Point3,
+    pub center1: Point3,
+    pub time0: f64,
+    pub time1: f64,
+    pub radius: f64,
+    pub material: Arc<dyn Material>,
+}
+
+impl MovingSphere {
+    pub fn new(
+        center0: Point3,
+        center1: Point3,
+        time0: f64,
+        time1: f64,
+        radius: f64,
+        material: Arc<dyn Material>,
+    ) -> Self {
+        Self {
+            center0,
+            center1,
+            time0,
+            time1,
+            radius,
+            material,
+        }
+    }
+
+    pub fn center(&self, time: f64) -> Point3 {
+        if self.time1 <= self.time0 {
+            return self.center0;
+        }
+
+        // 快门时间之外停在端点, 不沿运动方向外推
+        let t = ((time - self.time0) / (self.time1 - self.time0)).clamp(0.0, 1.0);
+        self.center0 + t * (self.center1 - self.center0)
+    }
+}
+
+impl Hittable for MovingSphere {
+    fn hit(&self, r: &Ray, min: f64, max: f64) -> Option<Record<'_>> {
+        let center = self.center(r.time());
+        hit_sphere(&center, self.radius, self.material.as_ref(), r, min, max)
+    }
+
+    // 包围盒要覆盖整段运动
+    fn bounding_box(&self) -> Option<Aabb> {
+        let r = Vec3::new(self.radius, self.radius, self.radius);
+        let box0 = Aabb::new(self.center0 - r, self.center0 + r);
+        let box1 = Aabb::new(self.center1 - r, self.center1 + r);
+        Some(Aabb::surrounding(&box0, &box1))
+    }
+}
+
+fn hit_sphere<'a>(
+    center: &Point3,
+    radius: f64,
+    material: &'a dyn Material,
+    r: &Ray,
+    min: f64,
+    max: f64,
+) -> Option<Record<'a>> {
+    let oc = r.origin() - center;
+
+    let a = r.direction().length_square();
+    let half_b = Vec3::dot(&oc, r.direction());
+    let c = oc.length_square() - radius * radius;
+    let discriminant = half_b * half_b - a * c;
+    if discriminant < 0.0 {
+        return None;
+    }
+
+    let sqrtd = discriminant.sqrt();
+    let mut root = (-half_b - sqrtd) / a;
+    if root < min || max < root {
+        root = (-half_b + sqrtd) / a;
         if root < min || max < root {
-            root = (-half_b + sqrtd) / a;
-            if root < min || max < root {
-                return None;
-            }
-        }
-
-        let p = r.at(root);
-        let outward_normal = (p - self.center) / self.radius;
-        let front_face = Vec3::dot(r.direction(), &outward_normal) < 0.0;
-        let normal = match front_face {
-            true => outward_normal,
-            false => -outward_normal,
-        };
-
-        Some(Record {
-            p,
-            t: root,
-            normal,
-            front_face,
-            material: self.material.clone(),
-        })
+            return None;
+        }
+    }
+
+    let outward_normal = (r.at(root) - *center) / radius;
+    let uv = Sphere::uv(&outward_normal);
+    let rec = Record::new(r, root, outward_normal, uv, material);
+    match Sphere::tangents(&outward_normal, radius) {
+        Some((dpdu, dpdv)) => Some(rec.with_tangents(dpdu, dpdv)),
+        None => Some(rec),
     }
 }
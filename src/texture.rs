@@ -0,0 +1,178 @@
+use std::path::Path;
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+
+use crate::{
+    perlin::Perlin,
+    vec3::{Color, Point3},
+};
+
+pub trait Texture: Send + Sync {
+    // 纹理在表面坐标 (u, v) 以及撞击点 p 处的颜色
+    fn value(&self, u: f64, v: f64, p: &Point3) -> Color;
+}
+
+// 纯色纹理
+pub struct SolidColor {
+    albedo: Color,
+}
+
+impl SolidColor {
+    pub fn new(albedo: Color) -> Self {
+        Self { albedo }
+    }
+}
+
+impl Texture for SolidColor {
+    fn value(&self, _: f64, _: f64, _: &Point3) -> Color {
+        self.albedo
+    }
+}
+
+// 三维空间中的棋盘格, 每个边长为 scale 的立方体交替使用两种纹理
+pub struct CheckerTexture {
+    inv_scale: f64,
+    even: Arc<dyn Texture>,
+    odd: Arc<dyn Texture>,
+}
+
+impl CheckerTexture {
+    pub fn new(scale: f64, even: Arc<dyn Texture>, odd: Arc<dyn Texture>) -> Self {
+        Self {
+            inv_scale: 1.0 / scale,
+            even,
+            odd,
+        }
+    }
+
+    pub fn from_colors(scale: f64, even: Color, odd: Color) -> Self {
+        Self::new(
+            scale,
+            Arc::new(SolidColor::new(even)),
+            Arc::new(SolidColor::new(odd)),
+        )
+    }
+}
+
+impl Texture for CheckerTexture {
+    fn value(&self, u: f64, v: f64, p: &Point3) -> Color {
+        let x = (self.inv_scale * p.x()).floor() as i64;
+        let y = (self.inv_scale * p.y()).floor() as i64;
+        let z = (self.inv_scale * p.z()).floor() as i64;
+
+        match (x + y + z) % 2 == 0 {
+            true => self.even.value(u, v, p),
+            false => self.odd.value(u, v, p),
+        }
+    }
+}
+
+// 图片纹理, (0, 0) 对应图片左下角, 超出 [0, 1] 的坐标按平铺处理
+pub struct ImageTexture {
+    width: usize,
+    height: usize,
+    data: Vec<Color>,
+}
+
+impl ImageTexture {
+    // 输出时做了 gamma 2 校正, 读取时反过来转换到线性空间
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::load(path.as_ref(), |c| (c as f64 / 255.0).powi(2))
+    }
+
+    // 不做 gamma 转换, 用于法线贴图这类存放数据而不是颜色的图片
+    pub fn open_linear<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::load(path.as_ref(), |c| c as f64 / 255.0)
+    }
+
+    fn load(path: &Path, decode: fn(u8) -> f64) -> Result<Self> {
+        let img = ::image::open(path)
+            .with_context(|| format!("open {}", path.display()))?
+            .to_rgb8();
+
+        let data = img
+            .pixels()
+            .map(|p| Color::new(decode(p[0]), decode(p[1]), decode(p[2])))
+            .collect();
+
+        Ok(Self {
+            width: img.width() as usize,
+            height: img.height() as usize,
+            data,
+        })
+    }
+}
+
+impl Texture for ImageTexture {
+    fn value(&self, u: f64, v: f64, _: &Point3) -> Color {
+        if self.data.is_empty() {
+            return Color::new(0.0, 1.0, 1.0);
+        }
+
+        let u = u - u.floor();
+        let v = 1.0 - (v - v.floor());
+
+        let i = ((u * self.width as f64) as usize).min(self.width - 1);
+        let j = ((v * self.height as f64) as usize).min(self.height - 1);
+        self.data[j * self.width + i]
+    }
+}
+
+// 噪声纹理的图案
+#[derive(Clone, Copy, Debug)]
+pub enum NoisePattern {
+    Smooth,     // 平滑的 Perlin 噪声
+    Turbulence, // 湍流
+    Marble,     // 大理石: 沿 z 轴的正弦条纹被湍流扰动
+    Wood,       // 木纹: 绕 y 轴的同心年轮被湍流扰动
+}
+
+// 程序化噪声纹理, 按图案计算出 [0, 1] 的值在两种颜色之间插值
+pub struct NoiseTexture {
+    noise: Perlin,
+    pattern: NoisePattern,
+    scale: f64, // 频率
+    a: Color,   // 值为 0 时的颜色
+    b: Color,   // 值为 1 时的颜色
+}
+
+impl NoiseTexture {
+    pub fn new(seed: u64, pattern: NoisePattern, scale: f64, a: Color, b: Color) -> Self {
+        Self {
+            noise: Perlin::new(seed),
+            pattern,
+            scale,
+            a,
+            b,
+        }
+    }
+
+    pub fn marble(seed: u64, scale: f64) -> Self {
+        let (dark, light) = (Color::new(0.1, 0.1, 0.12), Color::new(0.95, 0.95, 0.92));
+        Self::new(seed, NoisePattern::Marble, scale, dark, light)
+    }
+
+    pub fn wood(seed: u64, scale: f64) -> Self {
+        let (dark, light) = (Color::new(0.35, 0.18, 0.07), Color::new(0.72, 0.5, 0.28));
+        Self::new(seed, NoisePattern::Wood, scale, dark, light)
+    }
+}
+
+impl Texture for NoiseTexture {
+    fn value(&self, _: f64, _: f64, p: &Point3) -> Color {
+        let s = self.scale * *p;
+        let t = match self.pattern {
+            NoisePattern::Smooth => 0.5 * (1.0 + self.noise.noise(&s)),
+            NoisePattern::Turbulence => self.noise.turb(&s, 7),
+            NoisePattern::Marble => 0.5 * (1.0 + (s.z() + 10.0 * self.noise.turb(&s, 7)).sin()),
+            NoisePattern::Wood => {
+                let r = (s.x() * s.x() + s.z() * s.z()).sqrt() + 0.4 * self.noise.turb(&s, 4);
+                r - r.floor()
+            }
+        };
+
+        let t = t.clamp(0.0, 1.0);
+        (1.0 - t) * self.a + t * self.b
+    }
+}
@@ -0,0 +1,154 @@
+use anyhow::{anyhow, Result};
+
+use crate::{
+    aabb::Aabb,
+    hit::{Hittable, Record},
+    mat4::Mat4,
+    ray::Ray,
+    vec3::{Point3, Vec3},
+};
+
+// 对任意物体做仿射变换的实例
+// 求交时把光线变换到物体空间, 命中后再把撞击点和法线变换回世界空间
+// 需要共享同一份几何体时 T 可以是 Arc<dyn Hittable>
+pub struct Transform<T> {
+    object: T,
+    matrix: Mat4,  // 物体空间到世界空间
+    inverse: Mat4, // 世界空间到物体空间
+
+    // 法线要用逆矩阵的转置变换, 才能在非均匀缩放下保持与表面垂直
+    normal_matrix: Mat4,
+    bbox: Option<Aabb>,
+}
+
+impl<T: Hittable> Transform<T> {
+    // matrix 不可逆时返回错误
+    pub fn new(object: T, matrix: Mat4) -> Result<Self> {
+        let inverse = matrix
+            .inverse()
+            .ok_or_else(|| anyhow!("transform matrix is not invertible"))?;
+        Ok(Self::with_inverse(object, matrix, inverse))
+    }
+
+    // 逆矩阵已知时不需要再求逆
+    pub fn translate(object: T, offset: Vec3) -> Self {
+        Self::with_inverse(object, Mat4::translate(offset), Mat4::translate(-offset))
+    }
+
+    pub fn rotate_y(object: T, degrees: f64) -> Self {
+        Self::with_inverse(object, Mat4::rotate_y(degrees), Mat4::rotate_y(-degrees))
+    }
+
+    fn with_inverse(object: T, matrix: Mat4, inverse: Mat4) -> Self {
+        // 把物体包围盒的八个顶点变换到世界空间, 再求它们的包围盒
+        let bbox = object.bounding_box().map(|bbox| {
+            let corners = (0..8).map(|i| {
+                let pick = |bit: usize, axis: usize| match i & bit {
+                    0 => bbox.min[axis],
+                    _ => bbox.max[axis],
+                };
+                matrix.transform_point(&Point3::new(pick(1, 0), pick(2, 1), pick(4, 2)))
+            });
+            corners
+                .map(|c| Aabb::new(c, c))
+                .reduce(|a, b| Aabb::surrounding(&a, &b))
+                .unwrap()
+        });
+
+        Self {
+            object,
+            matrix,
+            inverse,
+            normal_matrix: inverse.transpose(),
+            bbox,
+        }
+    }
+}
+
+impl<T: Hittable> Transform<T> {
+    // 把光线变换到物体空间
+    // 方向不做归一化, 这样物体空间和世界空间中的 t 是一样的
+    fn local(&self, r: &Ray) -> Ray {
+        let origin = self.inverse.transform_point(r.origin());
+        let dir = self.inverse.transform_vector(r.direction());
+        Ray::new(origin, dir, r.time())
+    }
+
+    // 把物体空间的撞击记录变换回世界空间
+    fn to_world<'a>(&self, mut rec: Record<'a>) -> Record<'a> {
+        rec.p = self.matrix.transform_point(&rec.p);
+
+        rec.normal = self.normal_matrix.transform_vector(&rec.normal).unit();
+        rec.dpdu = self.matrix.transform_vector(&rec.dpdu);
+        rec.dpdv = self.matrix.transform_vector(&rec.dpdv);
+        rec
+    }
+}
+
+impl<T: Hittable> Hittable for Transform<T> {
+    fn hit(&self, r: &Ray, min: f64, max: f64) -> Option<Record<'_>> {
+        let rec = self.object.hit(&self.local(r), min, max)?;
+        Some(self.to_world(rec))
+    }
+
+    fn hit_surface(&self, r: &Ray, min: f64, max: f64) -> Option<Record<'_>> {
+        let rec = self.object.hit_surface(&self.local(r), min, max)?;
+        Some(self.to_world(rec))
+    }
+
+    fn transmittance(&self, r: &Ray, min: f64, max: f64) -> f64 {
+        self.object.transmittance(&self.local(r), min, max)
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        self.bbox
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::{material::Lambertian, sphere::Sphere, vec3::Color};
+
+    fn sphere() -> Sphere {
+        let material = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+        Sphere::new(Point3::default(), 1.0, material)
+    }
+
+    #[test]
+    fn singular_matrix() {
+        let matrix = Mat4::scale(Vec3::new(1.0, 1.0, 0.0));
+        assert!(Transform::new(sphere(), matrix).is_err());
+    }
+
+    // 非均匀缩放后法线仍然垂直于表面: 椭球 x²/4 + y² + z² = 1 的法线方向是 (x/4, y, z)
+    #[test]
+    fn scaled_normal() {
+        let ellipsoid = Transform::new(sphere(), Mat4::scale(Vec3::new(2.0, 1.0, 1.0))).unwrap();
+        let r = Ray::new(Point3::new(1.0, 5.0, 0.3), Vec3::new(0.0, -1.0, 0.0), 0.0);
+        let rec = ellipsoid.hit(&r, 0.001, f64::INFINITY).unwrap();
+
+        let p = rec.p;
+        assert!((p.x() * p.x() / 4.0 + p.y() * p.y() + p.z() * p.z() - 1.0).abs() < 1e-9);
+        let expected = Vec3::new(p.x() / 4.0, p.y(), p.z()).unit();
+        assert!((rec.normal - expected).near_zero());
+        assert!((rec.normal.length() - 1.0).abs() < 1e-9);
+    }
+
+    // translate 和 rotate_y 直接使用已知的逆矩阵, 结果应该与求逆得到的一样
+    #[test]
+    fn translate_rotate() {
+        let offset = Vec3::new(1.0, 2.0, 3.0);
+        let nested = Transform::translate(Transform::rotate_y(sphere(), 30.0), offset);
+        let matrix = Mat4::translate(offset) * Mat4::rotate_y(30.0);
+        let single = Transform::new(sphere(), matrix).unwrap();
+
+        let r = Ray::new(Point3::new(0.0, 2.5, -5.0), Vec3::new(0.2, 0.0, 1.0), 0.0);
+        let a = nested.hit(&r, 0.001, f64::INFINITY).unwrap();
+        let b = single.hit(&r, 0.001, f64::INFINITY).unwrap();
+        assert!((a.t - b.t).abs() < 1e-9);
+        assert!((a.normal - b.normal).near_zero());
+    }
+}
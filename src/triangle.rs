@@ -0,0 +1,89 @@
+use std::sync::Arc;
+
+use crate::{
+    aabb::Aabb,
+    hit::{Hittable, Record},
+    material::Material,
+    ray::Ray,
+    vec3::{Point3, Vec3},
+};
+
+pub struct Triangle {
+    pub vertices: [Point3; 3],       // 三个顶点, 逆时针方向为正面
+    pub material: Arc<dyn Material>, // 材质
+}
+
+impl Triangle {
+    pub fn new(v0: Point3, v1: Point3, v2: Point3, material: Arc<dyn Material>) -> Self {
+        Self {
+            vertices: [v0, v1, v2],
+            material,
+        }
+    }
+}
+
+impl Hittable for Triangle {
+    fn hit(&self, r: &Ray, min: f64, max: f64) -> Option<Record<'_>> {
+        let [v0, v1, v2] = &self.vertices;
+        let (t, b1, b2) = intersect(r, v0, v1, v2, min, max)?;
+
+        // 没有纹理坐标时直接用重心坐标, 对应的切向量就是两条边
+        let (e1, e2) = (v1 - v0, v2 - v0);
+        let outward_normal = Vec3::cross(&e1, &e2).unit();
+        let rec = Record::new(r, t, outward_normal, (b1, b2), self.material.as_ref());
+        Some(rec.with_tangents(e1, e2))
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        bounding_box(&self.vertices)
+    }
+}
+
+// Möller–Trumbore 算法
+// 把交点写成重心坐标 p = (1 - b1 - b2) * v0 + b1 * v1 + b2 * v2 和光线方程联立,
+// 用克莱姆法则解出 (t, b1, b2), 不需要先求出三角形所在平面
+pub(crate) fn intersect(
+    r: &Ray,
+    v0: &Point3,
+    v1: &Point3,
+    v2: &Point3,
+    min: f64,
+    max: f64,
+) -> Option<(f64, f64, f64)> {
+    let e1 = v1 - v0;
+    let e2 = v2 - v0;
+
+    let pvec = Vec3::cross(r.direction(), &e2);
+    let det = Vec3::dot(&e1, &pvec);
+
+    // 光线与三角形平行
+    if det.abs() < 1e-12 {
+        return None;
+    }
+
+    let inv_det = 1.0 / det;
+    let tvec = r.origin() - v0;
+    let b1 = Vec3::dot(&tvec, &pvec) * inv_det;
+    if !(0.0..=1.0).contains(&b1) {
+        return None;
+    }
+
+    let qvec = Vec3::cross(&tvec, &e1);
+    let b2 = Vec3::dot(r.direction(), &qvec) * inv_det;
+    if b2 < 0.0 || b1 + b2 > 1.0 {
+        return None;
+    }
+
+    let t = Vec3::dot(&e2, &qvec) * inv_det;
+    if t < min || max < t {
+        return None;
+    }
+
+    Some((t, b1, b2))
+}
+
+pub(crate) fn bounding_box(vertices: &[Point3; 3]) -> Option<Aabb> {
+    let [v0, v1, v2] = vertices;
+    let bbox = Aabb::new(*v0, *v1);
+    Some(Aabb::surrounding(&bbox, &Aabb::new(*v2, *v2)))
+}
@@ -1,6 +1,7 @@
-use std::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Sub, SubAssign};
+use std::f64::consts::PI;
+use std::ops::{Add, AddAssign, Div, DivAssign, Index, Mul, MulAssign, Neg, Sub, SubAssign};
 
-use rand::Rng;
+use crate::random;
 
 #[derive(Clone, Copy, Debug)]
 pub struct Vec3(f64, f64, f64);
@@ -29,6 +30,16 @@ impl Vec3 {
         )
     }
 
+    // 逐分量取最小值
+    pub fn min(a: &Self, b: &Self) -> Self {
+        Self::new(a.0.min(b.0), a.1.min(b.1), a.2.min(b.2))
+    }
+
+    // 逐分量取最大值
+    pub fn max(a: &Self, b: &Self) -> Self {
+        Self::new(a.0.max(b.0), a.1.max(b.1), a.2.max(b.2))
+    }
+
     // 反射 v 表示入射向量 n 表示法向量
     pub fn reflect(v: &Self, n: &Self) -> Self {
         *v - 2.0 * Self::dot(v, n) * (*n)
@@ -48,10 +59,9 @@ impl Vec3 {
     }
 
     pub fn random_range(min: f64, max: f64) -> Self {
-        let mut rng = rand::thread_rng();
-        let x = rng.gen_range(min..max);
-        let y = rng.gen_range(min..max);
-        let z = rng.gen_range(min..max);
+        let x = random::random_range(min, max);
+        let y = random::random_range(min, max);
+        let z = random::random_range(min, max);
         Self(x, y, z)
     }
 
@@ -76,6 +86,18 @@ impl Vec3 {
         Self::random_in_unit_sphere().unit()
     }
 
+    // 以 z 轴为中心按余弦分布的单位向量, 概率密度为 cosθ / π
+    pub fn random_cosine_direction() -> Self {
+        let r1 = random::random_double();
+        let r2 = random::random_double();
+
+        let phi = 2.0 * PI * r1;
+        let x = phi.cos() * r2.sqrt();
+        let y = phi.sin() * r2.sqrt();
+        let z = (1.0 - r2).sqrt();
+        Self(x, y, z)
+    }
+
     pub fn random_in_hemishpere(normal: &Self) -> Self {
         let unit_sphere = Self::random_in_unit_sphere();
         if Self::dot(normal, &unit_sphere) > 0.0 {
@@ -87,7 +109,6 @@ impl Vec3 {
 
     // 随机 Z 平面上半径在单位长度以内的圆盘
     pub fn random_in_uint_disk() -> Self {
-        let mut rng = rand::thread_rng();
         // loop {
         //     let p = Vec3::new(rng.gen_range(0.0..1.0), rng.gen_range(0.0..1.0), 0.0);
         //     if p.length_square() < 1.0 {
@@ -95,7 +116,7 @@ impl Vec3 {
         //     }
         // }
         for _ in 0..1000 {
-            let p = Vec3::new(rng.gen_range(0.0..1.0), rng.gen_range(0.0..1.0), 0.0);
+            let p = Vec3::new(random::random_double(), random::random_double(), 0.0);
             if p.length_square() < 1.0 {
                 return p;
             }
@@ -137,6 +158,19 @@ impl Default for Vec3 {
     }
 }
 
+impl Index<usize> for Vec3 {
+    type Output = f64;
+
+    fn index(&self, index: usize) -> &Self::Output {
+        match index {
+            0 => &self.0,
+            1 => &self.1,
+            2 => &self.2,
+            _ => panic!("index out of range: {}", index),
+        }
+    }
+}
+
 impl Neg for Vec3 {
     type Output = Self;
 
@@ -253,6 +287,59 @@ impl DivAssign<f64> for Vec3 {
     }
 }
 
+impl From<[f64; 3]> for Vec3 {
+    fn from(v: [f64; 3]) -> Self {
+        Self(v[0], v[1], v[2])
+    }
+}
+
 pub type Color = Vec3;
 
 pub type Point3 = Vec3;
+
+// 正交基, w 是给定的方向, 用于把局部坐标系中采样的方向转换到世界坐标
+#[derive(Clone, Copy, Debug)]
+pub struct Onb {
+    u: Vec3,
+    v: Vec3,
+    w: Vec3,
+}
+
+impl Onb {
+    pub fn new(n: &Vec3) -> Self {
+        let w = n.unit();
+        let a = match w.x().abs() > 0.9 {
+            true => Vec3::new(0.0, 1.0, 0.0),
+            false => Vec3::new(1.0, 0.0, 0.0),
+        };
+        let v = Vec3::cross(&w, &a).unit();
+        let u = Vec3::cross(&w, &v);
+        Self { u, v, w }
+    }
+
+    pub fn u(&self) -> &Vec3 {
+        &self.u
+    }
+
+    pub fn v(&self) -> &Vec3 {
+        &self.v
+    }
+
+    pub fn w(&self) -> &Vec3 {
+        &self.w
+    }
+
+    // 局部坐标 (a.x, a.y, a.z) 对应的世界坐标向量
+    pub fn local(&self, a: &Vec3) -> Vec3 {
+        a.x() * self.u + a.y() * self.v + a.z() * self.w
+    }
+
+    // 世界坐标向量在这组基下的局部坐标, 是 local 的逆变换
+    pub fn to_local(&self, a: &Vec3) -> Vec3 {
+        Vec3::new(
+            Vec3::dot(a, &self.u),
+            Vec3::dot(a, &self.v),
+            Vec3::dot(a, &self.w),
+        )
+    }
+}